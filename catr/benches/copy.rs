@@ -0,0 +1,68 @@
+//! Compares the plain copy path of `catr` with the line-by-line path.
+//!
+//! Both runs print exactly the same bytes: the generated input is printable ASCII,
+//! so `-v` doesn't change it but still forces every line through `print_line`.
+//!
+//! Run with `cargo bench --bench copy`. The input is 1 GiB by default,
+//! set `CATR_BENCH_BYTES` to use a different size.
+
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_SIZE: u64 = 1 << 30;
+const LINE: &[u8] = b"The quick brown fox jumps over the lazy dog 0123456789\n";
+
+fn generate_input(file: &File, size: u64) -> io::Result<()> {
+    let mut writer = BufWriter::new(file);
+    let mut written = 0u64;
+    while written < size {
+        let len = LINE.len().min((size - written) as usize);
+        writer.write_all(&LINE[..len])?;
+        written += len as u64;
+    }
+    writer.flush()
+}
+
+fn time_catr(args: &[&str], filename: &str) -> io::Result<(Duration, u64)> {
+    let start = Instant::now();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_catr"))
+        .args(args)
+        .arg(filename)
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let copied = io::copy(&mut stdout, &mut io::sink())?;
+    let status = child.wait()?;
+    assert!(status.success(), "catr failed with {status}");
+    Ok((start.elapsed(), copied))
+}
+
+fn report(name: &str, elapsed: Duration, bytes: u64) {
+    let throughput = bytes as f64 / elapsed.as_secs_f64() / (1 << 20) as f64;
+    println!("{name:<12} {:>10.3} s {throughput:>10.1} MiB/s", elapsed.as_secs_f64());
+}
+
+fn main() -> io::Result<()> {
+    let size = env::var("CATR_BENCH_BYTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_SIZE);
+    let input = tempfile::NamedTempFile::new()?;
+    generate_input(input.as_file(), size)?;
+    let filename = input.path().to_str().expect("temp path is valid UTF-8");
+
+    let (elapsed, copied) = time_catr(&[], filename)?;
+    assert_eq!(copied, size);
+    report("plain copy", elapsed, copied);
+
+    let (elapsed, copied) = time_catr(&["-v"], filename)?;
+    assert_eq!(copied, size);
+    report("line by line", elapsed, copied);
+
+    Ok(())
+}
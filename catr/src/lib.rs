@@ -1,4 +1,4 @@
-use std::{error::Error, io::{BufRead, self, BufReader}, fs::File};
+use std::{error::Error, io::{BufRead, self, BufReader, Write}, fs::File};
 use clap::Parser;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
@@ -17,6 +17,26 @@ struct Args {
     /// Number only nonblank lines in concatenate output
     #[arg(short = 'b', long = "number-nonblank")]
     number_nonblank_lines: bool,
+
+    /// Suppress repeated empty output lines
+    #[arg(short = 's', long = "squeeze-blank")]
+    squeeze_blank: bool,
+
+    /// Equivalent to -vET
+    #[arg(short = 'A', long = "show-all")]
+    show_all: bool,
+
+    /// Display $ at end of each line
+    #[arg(short = 'E', long = "show-ends")]
+    show_ends: bool,
+
+    /// Display TAB characters as ^I
+    #[arg(short = 'T', long = "show-tabs")]
+    show_tabs: bool,
+
+    /// Use ^ and M- notation, except for LFD and TAB
+    #[arg(short = 'v', long = "show-nonprinting")]
+    show_nonprinting: bool,
 }
 
 enum Counter {
@@ -37,6 +57,44 @@ impl From<&Args> for Counter {
     }
 }
 
+enum Squeeze {
+    On { previous_blank: bool },
+    Off,
+}
+
+impl From<&Args> for Squeeze {
+    fn from(args: &Args) -> Self {
+        if args.squeeze_blank {
+            Self::On { previous_blank: false }
+        } else {
+            Self::Off
+        }
+    }
+}
+
+/// Which of the invisible characters are made visible in the output.
+struct Display {
+    ends: bool,
+    tabs: bool,
+    nonprinting: bool,
+}
+
+impl Display {
+    fn is_plain(&self) -> bool {
+        !self.ends && !self.tabs && !self.nonprinting
+    }
+}
+
+impl From<&Args> for Display {
+    fn from(args: &Args) -> Self {
+        Self {
+            ends: args.show_all || args.show_ends,
+            tabs: args.show_all || args.show_tabs,
+            nonprinting: args.show_all || args.show_nonprinting,
+        }
+    }
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -44,42 +102,151 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-fn print_file(buf: Box<dyn BufRead>, counter: &mut Counter) {
-    for line in buf.lines() {
-        match line {
-            Ok(s) => print_line(&s, counter),
-            Err(error) => println!("Failed to read the line: {error}"),
-        }
+/// Output state that carries over from one file to the next.
+struct State {
+    counter: Counter,
+    squeeze: Squeeze,
+    /// `false` while the last file ended in the middle of a line, which the next file continues.
+    at_line_start: bool,
+}
+
+impl State {
+    fn is_plain(&self) -> bool {
+        matches!(self.counter, Counter::None) && matches!(self.squeeze, Squeeze::Off)
     }
 }
 
-fn print_line(line: &str, counter: &mut Counter) {
-    match counter {
-        Counter::None => println!("{line}"),
+/// Copies the file to stdout unchanged, without splitting it into lines.
+/// `io::copy` reads and writes in large blocks, and on Linux it hands the work
+/// over to the kernel with `copy_file_range`, `sendfile` or `splice` when it can.
+fn copy_file(filename: &str) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    match filename {
+        "-" => io::copy(&mut io::stdin().lock(), &mut stdout)?,
+        _ => io::copy(&mut File::open(filename)?, &mut stdout)?,
+    };
+    Ok(())
+}
+
+fn print_file(mut buf: Box<dyn BufRead>, state: &mut State, display: &Display) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    let mut line = Vec::new();
+    while buf.read_until(b'\n', &mut line)? > 0 {
+        print_line(&mut stdout, &line, state, display)?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Prints a line as raw bytes. The line keeps its newline, unless it's the last line
+/// of the file and the file doesn't end with one.
+fn print_line(out: &mut impl Write, line: &[u8], state: &mut State, display: &Display) -> io::Result<()> {
+    let (content, newline) = match line.strip_suffix(b"\n") {
+        Some(content) => (content, true),
+        None => (line, false),
+    };
+    let blank = content.is_empty();
+    let continued = !state.at_line_start;
+    state.at_line_start = newline;
+    if let Squeeze::On { previous_blank } = &mut state.squeeze {
+        if !continued {
+            let repeated = blank && *previous_blank;
+            *previous_blank = blank;
+            if repeated {
+                return Ok(());
+            }
+        }
+    }
+    // With -E a carriage return before the newline is shown as well, to make CRLF visible.
+    let (content, carriage_return) = match content.strip_suffix(b"\r") {
+        Some(content) if display.ends && newline => (content, true),
+        _ => (content, false),
+    };
+    match &mut state.counter {
+        _ if continued => {}
+        Counter::None => {}
         Counter::All { count } => {
-            println!("{:>6}\t{line}", count);
+            write!(out, "{:>6}\t", count)?;
             *count += 1
         },
         Counter::NonBlank { count } =>  {
-            if !line.is_empty() {
-                println!("{:>6}\t{line}", count);
+            if !blank {
+                write!(out, "{:>6}\t", count)?;
                 *count += 1
+            }
+        }
+    }
+    if display.tabs || display.nonprinting {
+        out.write_all(&make_visible(content, display))?;
+    } else {
+        out.write_all(content)?;
+    }
+    if carriage_return {
+        out.write_all(b"^M")?;
+    }
+    if display.ends && newline {
+        out.write_all(b"$")?;
+    }
+    if newline {
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Replaces tabs with `^I` and other non-printing bytes with `^X` and `M-` notation,
+/// as requested by the display options.
+fn make_visible(content: &[u8], display: &Display) -> Vec<u8> {
+    let mut visible = Vec::with_capacity(content.len());
+    for &byte in content {
+        if byte == b'\t' {
+            if display.tabs {
+                visible.extend_from_slice(b"^I");
             } else {
-                println!()
+                visible.push(byte);
             }
+            continue;
+        }
+        if !display.nonprinting {
+            visible.push(byte);
+            continue;
+        }
+        let byte = if byte >= 128 {
+            visible.extend_from_slice(b"M-");
+            byte - 128
+        } else {
+            byte
+        };
+        match byte {
+            0..=31 => visible.extend_from_slice(&[b'^', byte + 64]),
+            127 => visible.extend_from_slice(b"^?"),
+            _ => visible.push(byte),
         }
     }
+    visible
 }
 
-pub fn run() -> MyResult<()> {
+/// Prints the given files one after another.
+/// Returns `false` if any of them couldn't be printed, the errors are reported to stderr.
+pub fn run() -> MyResult<bool> {
     let args = Args::parse();
-    let mut counter = Counter::from(&args);
+    let mut state = State {
+        counter: Counter::from(&args),
+        squeeze: Squeeze::from(&args),
+        at_line_start: true,
+    };
+    let display = Display::from(&args);
+    let plain = state.is_plain() && display.is_plain();
+    let mut success = true;
     for filename in &args.files {
-        let file = open(&filename);
-        match file {
-            Ok(buf) => print_file(buf, &mut counter),
-            Err(error) => println!("Failed to open {filename}: {error}"),
+        let result = if plain {
+            copy_file(filename)
+        } else {
+            open(filename).and_then(|buf| print_file(buf, &mut state, &display))
+        };
+        if let Err(error) = result {
+            eprintln!("catr: {filename}: {error}");
+            success = false;
         }
     }
-    Ok(())
-}
\ No newline at end of file
+    Ok(success)
+}
@@ -1,6 +1,10 @@
 fn main() {
-    if let Err(e) = catr::run() {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match catr::run() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
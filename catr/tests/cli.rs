@@ -4,7 +4,7 @@ use predicates::prelude::*;
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -14,6 +14,9 @@ const EMPTY: &str = "empty.txt";
 const FOX: &str = "fox.txt";
 const SPIDERS: &str = "spiders.txt";
 const BUSTLE: &str = "the-bustle.txt";
+const SPECIAL: &str = "special.txt";
+const BLANKS: &str = "blanks.txt";
+const PARTIAL: &str = "partial.txt";
 const ALL: &str = "all";
 
 macro_rules! out {
@@ -76,12 +79,49 @@ fn dies_no_args() -> TestResult {
 #[test]
 fn skips_bad_file() -> TestResult {
     let bad = bad_file();
-    let expected = format!("{}:.*\\(os error 2\\)", bad);
+    let expected = format!("^catr: {}: .*\\(os error 2\\)\n$", bad);
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
-        .stdout(predicate::str::is_match(expected)?);
+        .failure()
+        .code(1)
+        .stdout("")
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+#[test]
+fn continues_after_bad_file() -> TestResult {
+    let bad = bad_file();
+    let expected = fs::read(expected!("fox.txt.out"))?.repeat(2);
+    Command::cargo_bin(PRG)?
+        .arg(input!(FOX))
+        .arg(&bad)
+        .arg(input!(FOX))
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(expected)
+        .stderr(predicate::str::is_match(format!(
+            "^catr: {}: .*\\(os error 2\\)\n$",
+            bad
+        ))?);
+    Ok(())
+}
+
+#[test]
+fn fails_reading_directory() -> TestResult {
+    let dir = path!("tests" / "inputs");
+    Command::cargo_bin(PRG)?
+        .arg(&dir)
+        .assert()
+        .failure()
+        .code(1)
+        .stdout("")
+        .stderr(
+            predicate::str::starts_with(format!("catr: {}: ", dir.display()))
+                .and(predicate::str::ends_with("(os error 21)\n")),
+        );
     Ok(())
 }
 
@@ -160,6 +200,94 @@ fn all_number_non_blank_lines() -> TestResult {
     run(&[SPIDERS, FOX, EMPTY, BUSTLE], &["-b"], &out_b!(ALL))
 }
 
+#[test]
+fn special() -> TestResult {
+    run(&[SPECIAL], &[], &out!(SPECIAL))
+}
+
+#[test]
+fn special_number_non_blank_lines() -> TestResult {
+    run(&[SPECIAL], &["-b"], &out_b!(SPECIAL))
+}
+
+#[test]
+fn special_show_all() -> TestResult {
+    run(&[SPECIAL], &["-A"], "special.txt.A.out")
+}
+
+#[test]
+fn special_show_ends() -> TestResult {
+    run(&[SPECIAL], &["-E"], "special.txt.E.out")
+}
+
+#[test]
+fn special_show_tabs() -> TestResult {
+    run(&[SPECIAL], &["-T"], "special.txt.T.out")
+}
+
+#[test]
+fn special_show_nonprinting() -> TestResult {
+    run(&[SPECIAL], &["-v"], "special.txt.v.out")
+}
+
+#[test]
+fn special_show_ends_tabs_nonprinting() -> TestResult {
+    run(&[SPECIAL], &["-v", "-E", "-T"], "special.txt.A.out")
+}
+
+#[test]
+fn special_number_lines_show_all() -> TestResult {
+    run(&[SPECIAL], &["-n", "-A"], "special.txt.nA.out")
+}
+
+#[test]
+fn stdin_show_all() -> TestResult {
+    run_stdin(SPECIAL, &["-A"], "special.txt.A.out")
+}
+#[test]
+fn blanks_squeeze() -> TestResult {
+    run(&[BLANKS], &["-s"], "blanks.txt.s.out")
+}
+
+#[test]
+fn blanks_squeeze_number_lines() -> TestResult {
+    run(&[BLANKS], &["-s", "-n"], "blanks.txt.s.n.out")
+}
+
+#[test]
+fn blanks_squeeze_number_non_blank_lines() -> TestResult {
+    run(&[BLANKS], &["-s", "-b"], "blanks.txt.s.b.out")
+}
+
+#[test]
+fn blanks_squeeze_across_files() -> TestResult {
+    run(&[BLANKS, EMPTY, BLANKS], &["-s"], "blanks.s.all.out")
+}
+
+#[test]
+fn blanks_squeeze_number_lines_across_files() -> TestResult {
+    run(&[BLANKS, BLANKS], &["-s", "-n"], "blanks.s.n.all.out")
+}
+
+#[test]
+fn partial_line_number_lines_across_files() -> TestResult {
+    run(&[PARTIAL, BLANKS], &["-n"], "partial.blanks.n.out")
+}
+
+#[test]
+fn partial_line_squeeze_across_files() -> TestResult {
+    run(&[PARTIAL, BLANKS], &["-s"], "partial.blanks.s.out")
+}
+
+#[test]
+fn partial_line_squeeze_number_lines_across_files() -> TestResult {
+    run(&[PARTIAL, BLANKS], &["-s", "-n"], "partial.blanks.s.n.out")
+}
+
+#[test]
+fn stdin_squeeze() -> TestResult {
+    run_stdin(BLANKS, &["-s"], "blanks.txt.s.out")
+}
 
 #[test]
 fn stdin() -> TestResult {
@@ -178,7 +306,7 @@ fn stdin_number_non_blank_lines() -> TestResult {
 
 fn run(files: &[&str], args: &[&str], expected_file: &str) -> TestResult {
     let expected_file = expected!(expected_file);
-    let expected = fs::read_to_string(&expected_file)?;
+    let expected = fs::read(&expected_file)?;
 
     let files: Vec<PathBuf> = files.iter().map(|x| input!(x)).collect();
     Command::cargo_bin(PRG)?
@@ -191,7 +319,7 @@ fn run(files: &[&str], args: &[&str], expected_file: &str) -> TestResult {
 
 fn run_stdin(input_file: &str, args: &[&str], expected_file: &str) -> TestResult {
     let expected_file = expected!(expected_file);
-    let expected = fs::read_to_string(&expected_file)?;
+    let expected = fs::read(&expected_file)?;
 
     let input_file = input!(input_file);
     Command::cargo_bin(PRG)?
@@ -1,21 +1,26 @@
-use std::{io::BufRead, ops::Range};
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, Read, Write},
+    ops::Range,
+};
 
 use clap::Parser;
+use regex::Regex;
 use shared_utils::MyResult;
 
 #[derive(Parser, Debug, Clone)]
 #[group(required = true, multiple = false)]
 struct RawExtract {
     /// Select only these bytes
-    #[arg(short, long, value_delimiter = ',')]
+    #[arg(short, long, value_delimiter = ',', allow_hyphen_values = true)]
     bytes: Vec<String>,
 
     /// Select only these characters
-    #[arg(short, long, value_delimiter = ',')]
+    #[arg(short, long, value_delimiter = ',', allow_hyphen_values = true)]
     characters: Vec<String>,
 
-    /// Select only these fields
-    #[arg(short, long, value_delimiter = ',')]
+    /// Select only these fields, by number or with --header also by name
+    #[arg(short, long, value_delimiter = ',', allow_hyphen_values = true)]
     fields: Vec<String>,
 }
 
@@ -29,71 +34,292 @@ struct RawArgs {
     extract: RawExtract,
 
     /// Use DELIM instead of TAB for field delimeter
-    #[arg(short, long, default_value_t = '\t')]
-    delim: char,
+    #[arg(short, long, default_value = "\t", value_parser = parse_delim)]
+    delim: String,
+
+    /// Split the fields on the matches of the regular expression PATTERN,
+    /// the output delimiter is a space by default
+    #[arg(long, value_name = "PATTERN", conflicts_with_all = ["delim", "bytes", "characters"])]
+    regex_delim: Option<Regex>,
+
+    /// Complement the set of selected bytes, characters or fields
+    #[arg(long)]
+    complement: bool,
+
+    /// Use STRING to separate the selected ranges in the output,
+    /// by default the input delimiter for fields and nothing for bytes and characters
+    #[arg(long, value_name = "STRING")]
+    output_delimiter: Option<String>,
+
+    /// Do not print lines that don't contain the delimiter
+    #[arg(short = 's', long, conflicts_with_all = ["bytes", "characters"])]
+    only_delimited: bool,
+
+    /// With -b, do not split multibyte characters
+    #[arg(short = 'n', conflicts_with_all = ["characters", "fields"])]
+    no_split: bool,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short, long)]
+    zero_terminated: bool,
+
+    /// Treat the first line of every file as a header, fields can be selected by its column names
+    #[arg(long, conflicts_with_all = ["bytes", "characters"])]
+    header: bool,
+
+    /// Print the fields in the order they were selected instead of the input order
+    #[arg(long, conflicts_with_all = ["bytes", "characters", "complement"])]
+    reorder: bool,
 }
 
 #[derive(Debug)]
 enum Extract {
-    Bytes(Vec<Range<usize>>),
+    Bytes(Vec<Range<usize>>, bool),
     Chars(Vec<Range<usize>>),
-    Fields(Vec<Range<usize>>, char),
+    Fields(Vec<Field>, Delimiter),
+}
+
+/// How the fields of a line are separated.
+#[derive(Debug)]
+enum Delimiter {
+    /// A single byte, the fields can be quoted like in CSV
+    Byte(u8),
+    /// A string of any length
+    Text(String),
+    Regex(Regex),
+}
+
+impl Delimiter {
+    /// The delimiter that joins the fields in the output, unless --output-delimiter is given.
+    fn output(&self) -> String {
+        match self {
+            Self::Byte(byte) => char::from(*byte).to_string(),
+            Self::Text(text) => text.clone(),
+            Self::Regex(_) => String::from(" "),
+        }
+    }
+
+    /// Only a single byte delimiter reads quoted fields, so only its output is quoted.
+    fn quotes(&self) -> bool {
+        matches!(self, Self::Byte(_))
+    }
+}
+
+fn parse_delim(value: &str) -> Result<String, String> {
+    if value.is_empty() {
+        Err(String::from("the delimiter must not be empty"))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// A selected field, columns names are resolved once the header of the file is read.
+#[derive(Debug)]
+enum Field {
+    Positions(Range<usize>),
+    Name(String),
 }
 
 #[derive(Debug)]
 struct Args {
     files: Vec<String>,
     extract: Extract,
+    output_delimiter: Option<String>,
+    only_delimited: bool,
+    terminator: u8,
+    complement: bool,
+    header: bool,
+    reorder: bool,
 }
 
+/// Parses `N`, `N-M`, `N-` (from N to the end of the line) or `-M` (from the start to M).
+/// Open ends are represented by `usize::MAX`.
 fn parse_range(value: &str) -> MyResult<Range<usize>> {
-    let split: Vec<&str> = value.split('-').collect();
-    match split.len() {
-        1 => {
-            let start = split[0].parse()?;
-            Ok(start..start + 1)
+    let parse_position = |position: &str| -> MyResult<usize> {
+        match position.parse()? {
+            0 => Err(Box::from("fields and positions are numbered from 1")),
+            position => Ok(position),
         }
-        2 => {
-            let start: usize = split[0].parse()?;
-            let end: usize = split[1].parse::<usize>()? + 1;
-            Ok(start..end)
+    };
+    let range = match value.split_once('-') {
+        None => {
+            let start = parse_position(value)?;
+            start..start.saturating_add(1)
         }
-        _ => Err(Box::from("not enough values")),
-    }
+        Some(("", "")) => return Err(Box::from("invalid range with no endpoint")),
+        Some(("", end)) => 1..parse_position(end)?.saturating_add(1),
+        Some((start, "")) => parse_position(start)?..usize::MAX,
+        Some((start, end)) => {
+            let start = parse_position(start)?;
+            let end = parse_position(end)?;
+            if end < start {
+                return Err(Box::from("invalid decreasing range"));
+            }
+            start..end.saturating_add(1)
+        }
+    };
+    Ok(range)
 }
 
+/// Parses the list of ranges and merges the overlapping ones,
+/// so the result is sorted and every position is selected only once.
 fn parse_ranges(values: &[String]) -> MyResult<Vec<Range<usize>>> {
-    values
+    let ranges = values
         .iter()
         .map(|v| parse_range(v).map_err(|e| format!("illegal list value: \"{v}\", error: '{e}'")))
-        .collect::<Result<Vec<Range<usize>>, _>>()
+        .collect::<Result<Vec<Range<usize>>, _>>()?;
+    Ok(merge_ranges(ranges))
+}
+
+/// Parses the list of fields, with a header the values that are not numbers
+/// or ranges are column names.
+fn parse_fields(values: &[String], header: bool) -> MyResult<Vec<Field>> {
+    values
+        .iter()
+        .map(|v| match parse_range(v) {
+            Ok(range) => Ok(Field::Positions(range)),
+            Err(_) if header && !v.chars().all(|c| c.is_ascii_digit() || c == '-') => {
+                Ok(Field::Name(v.clone()))
+            }
+            Err(e) => Err(format!("illegal list value: \"{v}\", error: '{e}'")),
+        })
+        .collect::<Result<Vec<Field>, _>>()
         .map_err(From::from)
 }
 
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Returns the positions that are not in the sorted, non-overlapping ranges.
+fn complement(ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut complement = Vec::with_capacity(ranges.len() + 1);
+    let mut start = 1;
+    for range in ranges {
+        if start < range.start {
+            complement.push(start..range.start);
+        }
+        start = range.end;
+    }
+    if start < usize::MAX {
+        complement.push(start..usize::MAX);
+    }
+    complement
+}
+
 fn get_args() -> MyResult<Args> {
     let args = RawArgs::parse();
+    let ranges = |values: &[String]| -> MyResult<Vec<Range<usize>>> {
+        let ranges = parse_ranges(values)?;
+        Ok(if args.complement {
+            complement(&ranges)
+        } else {
+            ranges
+        })
+    };
     let extract = if !args.extract.bytes.is_empty() {
-        Extract::Bytes(parse_ranges(&args.extract.bytes)?)
+        Extract::Bytes(ranges(&args.extract.bytes)?, args.no_split)
     } else if !args.extract.characters.is_empty() {
-        Extract::Chars(parse_ranges(&args.extract.characters)?)
+        Extract::Chars(ranges(&args.extract.characters)?)
     } else if !args.extract.fields.is_empty() {
-        Extract::Fields(parse_ranges(&args.extract.fields)?, args.delim)
+        let delimiter = match (args.regex_delim, args.delim.as_bytes()) {
+            (Some(regex), _) => Delimiter::Regex(regex),
+            (None, [byte]) => Delimiter::Byte(*byte),
+            (None, _) => Delimiter::Text(args.delim),
+        };
+        Extract::Fields(parse_fields(&args.extract.fields, args.header)?, delimiter)
     } else {
         return Err(Box::from("Failed to extract ranges"));
     };
     Ok(Args {
         extract,
         files: args.files,
+        output_delimiter: args.output_delimiter,
+        only_delimited: args.only_delimited,
+        terminator: if args.zero_terminated { b'\0' } else { b'\n' },
+        complement: args.complement,
+        header: args.header,
+        reorder: args.reorder,
     })
 }
 
-fn process_ranges<T>(
+/// Turns the selected fields into ranges of positions, looking up the column names in the header.
+/// The ranges are merged unless the fields are reordered.
+fn resolve_fields(
+    fields: &[Field],
+    header: Option<&csv::StringRecord>,
+    args: &Args,
+) -> MyResult<Vec<Range<usize>>> {
+    let ranges = fields
+        .iter()
+        .map(|field| match field {
+            Field::Positions(range) => Ok(range.clone()),
+            Field::Name(name) => header
+                .and_then(|header| header.iter().position(|column| column == name))
+                .map(|index| index + 1..index + 2)
+                .ok_or_else(|| format!("unknown column name: \"{name}\"")),
+        })
+        .collect::<Result<Vec<Range<usize>>, _>>()?;
+    if args.reorder {
+        return Ok(ranges);
+    }
+    let ranges = merge_ranges(ranges);
+    Ok(if args.complement {
+        complement(&ranges)
+    } else {
+        ranges
+    })
+}
+
+/// Quotes the field as RFC 4180 does when it couldn't be read back otherwise, because it contains
+/// the delimiter, a line break or the terminator. Other fields are printed as they are, like in cut.
+fn quote_field<'a>(field: &'a str, delim: &str, terminator: u8) -> Cow<'a, str> {
+    if field.contains(delim) || field.contains(['\r', '\n', terminator as char]) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn write_fields(
+    out: &mut impl Write,
+    record: &csv::StringRecord,
+    ranges: &[Range<usize>],
+    delim: &str,
+    terminator: u8,
+    quote: bool,
+) -> io::Result<()> {
+    let values: Vec<Cow<str>> = ranges
+        .iter()
+        .flat_map(|range| range.start..range.end.min(record.len() + 1))
+        .filter_map(|position| record.get(position - 1))
+        .map(|field| {
+            if quote {
+                quote_field(field, delim, terminator)
+            } else {
+                Cow::Borrowed(field)
+            }
+        })
+        .collect();
+    out.write_all(values.join(delim).as_bytes())?;
+    out.write_all(&[terminator])
+}
+
+fn process_ranges<T, S>(
     elements: &mut dyn Iterator<Item = T>,
     ranges: &[Range<usize>],
-    stringify: impl Fn(Vec<T>) -> String,
-) -> Vec<String> {
+    stringify: impl Fn(Vec<T>) -> S,
+) -> Vec<S> {
     let mut counter = 1;
-    let mut values: Vec<String> = Vec::new();
+    let mut values: Vec<S> = Vec::new();
     for range in ranges {
         if counter >= range.end {
             continue;
@@ -113,39 +339,260 @@ fn process_ranges<T>(
     values
 }
 
-fn process_input(file: impl BufRead, extract: &Extract) -> MyResult<()> {
-    match extract {
-        Extract::Bytes(ranges) => {
-            for line in file.lines() {
+/// Selects the bytes without splitting characters: a character is selected with all
+/// of its bytes when its last byte is selected, and not at all otherwise.
+/// Bytes that are not valid UTF-8 are taken as single characters.
+fn select_whole_chars(line: &[u8], ranges: &[Range<usize>]) -> Vec<Vec<u8>> {
+    let mut values: Vec<Vec<u8>> = Vec::new();
+    let mut current_range = None;
+    let mut start = 0;
+    let mut select = |len: usize| {
+        let end = start + len;
+        if let Some(index) = ranges.iter().position(|range| range.contains(&end)) {
+            if current_range != Some(index) {
+                values.push(Vec::new());
+                current_range = Some(index);
+            }
+            if let Some(value) = values.last_mut() {
+                value.extend_from_slice(&line[start..end]);
+            }
+        }
+        start = end;
+    };
+    for chunk in line.utf8_chunks() {
+        chunk.valid().chars().for_each(|c| select(c.len_utf8()));
+        chunk.invalid().iter().for_each(|_| select(1));
+    }
+    values
+}
+
+/// A line of the input.
+enum Line {
+    /// The line contains the delimiter and is split into fields
+    Fields(csv::StringRecord),
+    /// The line doesn't contain the delimiter, it's kept as it was read
+    Undelimited(Vec<u8>),
+}
+
+/// Passes the bytes of the file through to the CSV reader and keeps them,
+/// so a record can be echoed exactly as it was written.
+struct Recorder<R> {
+    inner: R,
+    buf: Vec<u8>,
+    /// The position in the file of the first byte in `buf`
+    offset: u64,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(out)?;
+        self.buf.extend_from_slice(&out[..len]);
+        Ok(len)
+    }
+}
+
+impl<R> Recorder<R> {
+    fn raw(&self, range: Range<u64>) -> &[u8] {
+        &self.buf[(range.start - self.offset) as usize..(range.end - self.offset) as usize]
+    }
+
+    /// Forgets the bytes before `position`, in large enough batches to not move the rest for every record.
+    fn discard(&mut self, position: u64) {
+        let len = (position - self.offset) as usize;
+        if len > self.buf.len() / 2 {
+            self.buf.drain(..len);
+            self.offset = position;
+        }
+    }
+}
+
+/// Reads the records of the file with a single byte delimiter, which can be quoted like in CSV.
+struct CsvLines<R> {
+    reader: csv::Reader<Recorder<R>>,
+    delimiter: u8,
+    terminator: u8,
+    /// Empty lines that the CSV reader skipped before the next record
+    blank_lines: usize,
+    next_line: Option<Line>,
+    done: bool,
+}
+
+impl<R: Read> CsvLines<R> {
+    fn new(file: R, delimiter: u8, terminator: u8) -> Self {
+        let recorder = Recorder {
+            inner: file,
+            buf: Vec::new(),
+            offset: 0,
+        };
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .terminator(csv::Terminator::Any(terminator))
+            .from_reader(recorder);
+        Self {
+            reader,
+            delimiter,
+            terminator,
+            blank_lines: 0,
+            next_line: None,
+            done: false,
+        }
+    }
+
+    /// Reads the next record, and counts the empty lines before it.
+    fn read_record(&mut self) -> MyResult<()> {
+        let start = self.reader.position().byte();
+        let mut record = csv::StringRecord::new();
+        let found = self.reader.read_record(&mut record)?;
+        let end = self.reader.position().byte();
+        let recorder = self.reader.get_mut();
+        let raw = recorder.raw(start..end);
+        let record_start = raw
+            .iter()
+            .position(|byte| *byte != self.terminator)
+            .unwrap_or(raw.len());
+        self.blank_lines = record_start;
+        if found {
+            let raw = &raw[record_start..];
+            let raw = raw.strip_suffix(&[self.terminator]).unwrap_or(raw);
+            // Like in cut, a line is undelimited when it doesn't contain the delimiter at all, quoted or not.
+            self.next_line = Some(if record.len() == 1 && !raw.contains(&self.delimiter) {
+                Line::Undelimited(raw.to_vec())
+            } else {
+                Line::Fields(record)
+            });
+        } else {
+            self.done = true;
+        }
+        recorder.discard(end);
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for CsvLines<R> {
+    type Item = MyResult<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.blank_lines > 0 {
+                self.blank_lines -= 1;
+                return Some(Ok(Line::Undelimited(Vec::new())));
+            }
+            if let Some(line) = self.next_line.take() {
+                return Some(Ok(line));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.read_record() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Reads the lines of the file and splits those that contain the delimiter into fields.
+fn read_lines<'a>(
+    file: impl BufRead + 'a,
+    delimiter: &'a Delimiter,
+    terminator: u8,
+) -> Box<dyn Iterator<Item = MyResult<Line>> + 'a> {
+    match delimiter {
+        Delimiter::Byte(byte) => Box::new(CsvLines::new(file, *byte, terminator)),
+        Delimiter::Text(_) | Delimiter::Regex(_) => {
+            Box::new(file.split(terminator).map(move |line| {
+                let line = String::from_utf8(line?)?;
+                let record: csv::StringRecord = match delimiter {
+                    Delimiter::Text(text) if line.contains(text.as_str()) => {
+                        line.split(text.as_str()).collect()
+                    }
+                    Delimiter::Regex(regex) if regex.is_match(&line) => {
+                        regex.split(&line).collect()
+                    }
+                    _ => return Ok(Line::Undelimited(line.into_bytes())),
+                };
+                Ok(Line::Fields(record))
+            }))
+        }
+    }
+}
+
+fn process_input(file: impl BufRead, args: &Args) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    match &args.extract {
+        Extract::Bytes(ranges, no_split) => {
+            let delim = args.output_delimiter.as_deref().unwrap_or("");
+            for line in file.split(args.terminator) {
                 let line = line?;
-                let mut bytes = line.bytes();
-                let values: Vec<String> = process_ranges(bytes.by_ref(), ranges, |b| {
-                    String::from_utf8_lossy(&b).to_string()
-                });
-                println!("{}", values.join(""));
+                let values = if *no_split {
+                    select_whole_chars(&line, ranges)
+                } else {
+                    let mut bytes = line.into_iter();
+                    process_ranges(bytes.by_ref(), ranges, |b| b)
+                };
+                stdout.write_all(&values.join(delim.as_bytes()))?;
+                stdout.write_all(&[args.terminator])?;
             }
         }
         Extract::Chars(ranges) => {
-            for line in file.lines() {
-                let line = line?;
+            let delim = args.output_delimiter.as_deref().unwrap_or("");
+            for line in file.split(args.terminator) {
+                let line = String::from_utf8(line?)?;
                 let mut chars = line.chars();
                 let values: Vec<String> =
                     process_ranges(chars.by_ref(), ranges, |c| c.into_iter().collect());
-                println!("{}", values.join(""));
+                stdout.write_all(values.join(delim).as_bytes())?;
+                stdout.write_all(&[args.terminator])?;
             }
         }
-        Extract::Fields(ranges, delim) => {
-            let mut rdr = csv::ReaderBuilder::new()
-                .has_headers(false)
-                .delimiter(*delim as u8)
-                .from_reader(file);
-            let delim = format!("{delim}");
-            for record in rdr.records() {
-                let record = record?;
-                let mut fields = record.into_iter();
-                let values: Vec<String> =
-                    process_ranges(fields.by_ref(), ranges, |c| c.join(&delim));
-                println!("{}", values.join(&format!("{delim}")));
+        Extract::Fields(fields, delimiter) => {
+            let delim = match &args.output_delimiter {
+                Some(delim) => delim.clone(),
+                None => delimiter.output(),
+            };
+            let quote = delimiter.quotes();
+            let mut lines = read_lines(file, delimiter, args.terminator);
+            let ranges = if args.header {
+                let header = match lines.next().transpose()? {
+                    None => return Ok(()),
+                    Some(Line::Fields(header)) => header,
+                    Some(Line::Undelimited(line)) => {
+                        csv::StringRecord::from(vec![String::from_utf8_lossy(&line)])
+                    }
+                };
+                let ranges = resolve_fields(fields, Some(&header), args)?;
+                write_fields(
+                    &mut stdout,
+                    &header,
+                    &ranges,
+                    &delim,
+                    args.terminator,
+                    quote,
+                )?;
+                ranges
+            } else {
+                resolve_fields(fields, None, args)?
+            };
+            for line in lines {
+                match line? {
+                    Line::Fields(record) => write_fields(
+                        &mut stdout,
+                        &record,
+                        &ranges,
+                        &delim,
+                        args.terminator,
+                        quote,
+                    )?,
+                    // Like in cut, a line without the delimiter is printed as it is, unless -s is given.
+                    Line::Undelimited(line) => {
+                        if !args.only_delimited {
+                            stdout.write_all(&line)?;
+                            stdout.write_all(&[args.terminator])?;
+                        }
+                    }
+                }
             }
         }
     }
@@ -161,7 +608,7 @@ pub fn run() -> MyResult<()> {
             continue;
         }
         let file = file.unwrap();
-        process_input(file, &args.extract)?;
+        process_input(file, &args)?;
     }
     Ok(())
 }
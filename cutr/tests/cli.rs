@@ -1,3 +1,5 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
@@ -9,6 +11,7 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const MIXED: &str = "tests/inputs/mixed.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -92,16 +95,25 @@ fn dies_bad_digit_chars() -> TestResult {
 fn dies_empty_delimiter() -> TestResult {
     dies(
         &[CSV, "-f", "1", "-d", ""],
-        "invalid value '' for '--delim <DELIM>': cannot parse char from empty string",
+        "invalid value '' for '--delim <DELIM>': the delimiter must not be empty",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex_delimiter() -> TestResult {
+    dies(
+        &[CSV, "-f", "1", "--regex-delim", "("],
+        "invalid value '(' for '--regex-delim <PATTERN>': regex parse error",
     )
 }
 
 // --------------------------------------------------
 #[test]
-fn dies_bad_delimiter() -> TestResult {
+fn dies_delimiter_and_regex_delimiter() -> TestResult {
     dies(
-        &[CSV, "-f", "1", "-d", ",,"],
-        "invalid value ',,' for '--delim <DELIM>': too many characters in string",
+        &[CSV, "-f", "1", "-d", ",", "--regex-delim", ","],
+        "cannot be used with",
     )
 }
 
@@ -158,14 +170,13 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
 }
 
 // --------------------------------------------------
-fn run_lossy(args: &[&str], expected_file: &str) -> TestResult {
-    let contents = fs::read(expected_file)?;
-    let expected = String::from_utf8_lossy(&contents);
+fn run_bytes(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::str::contains(expected));
+        .stdout(expected);
     Ok(())
 }
 
@@ -274,7 +285,7 @@ fn tsv_b2() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn tsv_b8() -> TestResult {
-    run_lossy(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
+    run_bytes(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
 }
 
 // --------------------------------------------------
@@ -292,7 +303,7 @@ fn tsv_b2_3() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn tsv_b1_8() -> TestResult {
-    run_lossy(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
+    run_bytes(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
 }
 
 // --------------------------------------------------
@@ -336,3 +347,504 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn dies_zero_position() -> TestResult {
+    dies(
+        &[TSV, "-f", "0-2"],
+        "illegal list value: \"0-2\", error: 'fields and positions are numbered from 1'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_decreasing_range() -> TestResult {
+    dies(
+        &[TSV, "-c", "3-1"],
+        "illegal list value: \"3-1\", error: 'invalid decreasing range'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_range_without_endpoints() -> TestResult {
+    dies(
+        &[TSV, "-b", "-"],
+        "illegal list value: \"-\", error: 'invalid range with no endpoint'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_to_end() -> TestResult {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f_start_to_2() -> TestResult {
+    run(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f3_1_unsorted() -> TestResult {
+    run(&[TSV, "-f", "3,1"], "tests/expected/movies1.tsv.f3,1.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f1_2_2_3_overlapping() -> TestResult {
+    run(
+        &[TSV, "-f", "1-2,2-3"],
+        "tests/expected/movies1.tsv.f1-2,2-3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b3_to_end() -> TestResult {
+    run(&[TSV, "-b", "3-"], "tests/expected/movies1.tsv.b3-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_complement() -> TestResult {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f1_3_to_end_complement() -> TestResult {
+    run(
+        &[CSV, "-f", "1,3-", "-d", ",", "--complement"],
+        "tests/expected/movies1.csv.f1,3-.dcomma.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_c_start_to_3_6_complement() -> TestResult {
+    run(
+        &[TSV, "-c", "-3,6", "--complement"],
+        "tests/expected/movies1.tsv.c-3,6.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_only_delimited_without_fields() -> TestResult {
+    dies(
+        &[TSV, "-c", "1", "-s"],
+        "cannot be used with '--only-delimited'",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prints_undelimited_lines() -> TestResult {
+    run(
+        &["tests/inputs/undelimited.tsv", "-f", "1,3"],
+        "tests/expected/undelimited.tsv.f1,3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> TestResult {
+    run(
+        &["tests/inputs/undelimited.tsv", "-f", "1,3", "-s"],
+        "tests/expected/undelimited.tsv.f1,3.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fields_output_delimiter() -> TestResult {
+    run(
+        &[
+            "tests/inputs/undelimited.tsv",
+            "-f",
+            "1,3",
+            "--output-delimiter",
+            " | ",
+        ],
+        "tests/expected/undelimited.tsv.f1,3.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fields_range_output_delimiter() -> TestResult {
+    run(
+        &[TSV, "-f", "1-", "--output-delimiter", ";"],
+        "tests/expected/movies1.tsv.f1-.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn chars_output_delimiter() -> TestResult {
+    run(
+        &[TSV, "-c", "1-3,5-6", "--output-delimiter", ":"],
+        "tests/expected/movies1.tsv.c1-3,5-6.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_output_delimiter() -> TestResult {
+    run(
+        &[TSV, "-b", "1,2,4", "--output-delimiter", "::"],
+        "tests/expected/movies1.tsv.b1,2,4.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fields_zero_terminated() -> TestResult {
+    run(
+        &["tests/inputs/records.csv", "-z", "-d", ",", "-f", "2"],
+        "tests/expected/records.csv.f2.dcomma.z.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited_zero_terminated() -> TestResult {
+    run(
+        &[
+            "tests/inputs/records.csv",
+            "-z",
+            "-s",
+            "-d",
+            ",",
+            "-f",
+            "1,3",
+        ],
+        "tests/expected/records.csv.f1,3.dcomma.s.z.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn chars_zero_terminated() -> TestResult {
+    run(
+        &["tests/inputs/records.csv", "-z", "-c", "1-2"],
+        "tests/expected/records.csv.c1-2.z.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_column_name() -> TestResult {
+    dies(
+        &[TSV, "--header", "-f", "title,rating"],
+        "unknown column name: \"rating\"",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_column_name_without_header() -> TestResult {
+    dies(&[TSV, "-f", "title"], "illegal list value: \"title\"")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_reorder_complement() -> TestResult {
+    dies(
+        &[TSV, "-f", "1", "--reorder", "--complement"],
+        "cannot be used with",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_names() -> TestResult {
+    run(
+        &[
+            "tests/inputs/movies2.csv",
+            "-d",
+            ",",
+            "--header",
+            "-f",
+            "director,title",
+        ],
+        "tests/expected/movies2.csv.header.fdirector,title.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_names_reorder() -> TestResult {
+    run(
+        &[
+            "tests/inputs/movies2.csv",
+            "-d",
+            ",",
+            "--header",
+            "-f",
+            "director,title",
+            "--reorder",
+        ],
+        "tests/expected/movies2.csv.header.fdirector,title.reorder.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_names_and_numbers() -> TestResult {
+    run(
+        &[
+            "tests/inputs/books.csv",
+            "-d",
+            ",",
+            "--header",
+            "-f",
+            "Title,2",
+        ],
+        "tests/expected/books.csv.header.fTitle,2.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_numbers_reorder() -> TestResult {
+    run(
+        &[TSV, "--header", "-f", "3,1-2", "--reorder"],
+        "tests/expected/movies1.tsv.header.f3,1-2.reorder.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quotes_fields_with_delimiter() -> TestResult {
+    run(
+        &["tests/inputs/movies2.csv", "-d", ",", "-f", "1"],
+        "tests/expected/movies2.csv.f1.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quotes_only_for_output_delimiter() -> TestResult {
+    run(
+        &[
+            "tests/inputs/books.csv",
+            "-d",
+            ",",
+            "-f",
+            "1,3",
+            "--output-delimiter",
+            ";",
+        ],
+        "tests/expected/books.csv.f1,3.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multibyte_delimiter() -> TestResult {
+    run(
+        &["tests/inputs/books.section.txt", "-d", "§", "-f", "1,3"],
+        "tests/expected/books.section.txt.f1,3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multibyte_delimiter_only_delimited() -> TestResult {
+    run(
+        &[
+            "tests/inputs/books.section.txt",
+            "-d",
+            "§",
+            "-f",
+            "1,3",
+            "-s",
+            "--output-delimiter",
+            " / ",
+        ],
+        "tests/expected/books.section.txt.f1,3.s.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multichar_delimiter() -> TestResult {
+    run(
+        &["tests/inputs/movies1.colons.txt", "-d", "::", "-f", "2-"],
+        "tests/expected/movies1.colons.txt.f2-.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter() -> TestResult {
+    run(
+        &["tests/inputs/ps.txt", "--regex-delim", r"\s+", "-f", "1,2"],
+        "tests/expected/ps.txt.regex.f1,2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter_output_delimiter() -> TestResult {
+    run(
+        &[
+            "tests/inputs/ps.txt",
+            "--regex-delim",
+            r"\s+",
+            "-f",
+            "2-",
+            "--output-delimiter",
+            ",",
+        ],
+        "tests/expected/ps.txt.regex.f2-.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter_header() -> TestResult {
+    run(
+        &[
+            "tests/inputs/ps.txt",
+            "--regex-delim",
+            r"\s+",
+            "--header",
+            "-f",
+            "COMMAND,USER",
+            "--reorder",
+        ],
+        "tests/expected/ps.txt.regex.header.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_no_split_without_bytes() -> TestResult {
+    dies(&[TSV, "-c", "1", "-n"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b1_3() -> TestResult {
+    run_bytes(&[MIXED, "-b", "1-3"], "tests/expected/mixed.txt.b1-3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b2_5() -> TestResult {
+    run_bytes(&[MIXED, "-b", "2-5"], "tests/expected/mixed.txt.b2-5.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b4_to_end() -> TestResult {
+    run_bytes(&[MIXED, "-b", "4-"], "tests/expected/mixed.txt.b4-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b1_3_5() -> TestResult {
+    run_bytes(
+        &[MIXED, "-b", "1,3,5"],
+        "tests/expected/mixed.txt.b1,3,5.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b1_3_no_split() -> TestResult {
+    run_bytes(
+        &[MIXED, "-n", "-b", "1-3"],
+        "tests/expected/mixed.txt.b1-3.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b2_5_no_split() -> TestResult {
+    run_bytes(
+        &[MIXED, "-n", "-b", "2-5"],
+        "tests/expected/mixed.txt.b2-5.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b4_to_end_no_split() -> TestResult {
+    run_bytes(
+        &[MIXED, "-n", "-b", "4-"],
+        "tests/expected/mixed.txt.b4-.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_b1_3_5_no_split() -> TestResult {
+    run_bytes(
+        &[MIXED, "-n", "-b", "1,3,5"],
+        "tests/expected/mixed.txt.b1,3,5.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn mixed_bstart_to_2_7_to_end_no_split() -> TestResult {
+    run_bytes(
+        &[MIXED, "-n", "-b", "-2,7-"],
+        "tests/expected/mixed.txt.b-2,7-.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn keeps_blank_and_quoted_undelimited_lines() -> TestResult {
+    run(
+        &["tests/inputs/blank_quoted.csv", "-d", ",", "-f", "1"],
+        "tests/expected/blank_quoted.csv.f1.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited_keeps_quoted_delimiter() -> TestResult {
+    run(
+        &["tests/inputs/blank_quoted.csv", "-d", ",", "-f", "2", "-s"],
+        "tests/expected/blank_quoted.csv.f2.dcomma.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multichar_delimiter_keeps_quotes() -> TestResult {
+    run(
+        &["tests/inputs/quotes.colons.txt", "-d", "::", "-f", "2"],
+        "tests/expected/quotes.colons.txt.f2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quoted_line_break() -> TestResult {
+    run(
+        &["tests/inputs/multiline.csv", "-d", ",", "-f", "2,3"],
+        "tests/expected/multiline.csv.f2,3.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_keeps_quotes() -> TestResult {
+    run(
+        &["tests/inputs/quotes.tsv", "-f", "2"],
+        "tests/expected/quotes.tsv.f2.out",
+    )
+}
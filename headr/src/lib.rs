@@ -1,4 +1,5 @@
-use std::io::{BufRead, self, BufReader};
+use std::collections::VecDeque;
+use std::io::{BufRead, self, BufReader, Read, Write};
 use std::fs::File;
 
 use clap::Parser;
@@ -7,15 +8,34 @@ type MyResult<T> = Result<T, Box<dyn std::error::Error>>;
 
 enum Mode {
     Bytes(usize),
+    Chars(usize),
     Lines(usize),
+    AllButLastBytes(usize),
+    AllButLastLines(usize),
+}
+
+/// A count of lines or bytes, a leading `-` means "all but the last N".
+/// The sign is kept apart from the size, so that `-0` still means "all".
+#[derive(Debug, Clone, Copy)]
+struct Count {
+    negative: bool,
+    size: usize,
 }
 
 impl From<&Args> for Mode {
     fn from(value: &Args) -> Self {
         if let Some(count) = value.bytes {
-            Mode::Bytes(count)
+            if count.negative {
+                Mode::AllButLastBytes(count.size)
+            } else {
+                Mode::Bytes(count.size)
+            }
+        } else if let Some(count) = value.chars {
+            Mode::Chars(count)
+        } else if value.lines.negative {
+            Mode::AllButLastLines(value.lines.size)
         } else {
-            Mode::Lines(value.lines)
+            Mode::Lines(value.lines.size)
         }
     }
 }
@@ -27,13 +47,48 @@ pub struct Args {
     #[arg(required = true)]
     files: Vec<String>,
 
-    /// Number of bytes to print
-    #[arg(short = 'c', long, conflicts_with = "lines")]
-    bytes: Option<usize>,
+    /// Number of bytes to print, or all but the last N bytes if negative.
+    /// Accepts size suffixes such as K, KB, KiB, M, MB, MiB
+    #[arg(
+        short = 'c',
+        long,
+        conflicts_with = "lines",
+        allow_hyphen_values = true,
+        value_parser = parse_count
+    )]
+    bytes: Option<Count>,
+
+    /// Number of UTF-8 characters to print
+    #[arg(long, conflicts_with_all = ["lines", "bytes"], value_parser = shared_utils::parse_size_arg)]
+    chars: Option<usize>,
 
-    /// Number of lines to print
-    #[arg(short = 'n', long, default_value_t = 10, conflicts_with = "bytes")]
-    lines: usize,
+    /// Number of lines to print, or all but the last N lines if negative
+    #[arg(
+        short = 'n',
+        long,
+        default_value = "10",
+        conflicts_with = "bytes",
+        allow_hyphen_values = true,
+        value_parser = parse_count
+    )]
+    lines: Count,
+
+    /// Never print headers with file names
+    #[arg(short, long, visible_alias = "silent", overrides_with = "verbose")]
+    quiet: bool,
+
+    /// Always print headers with file names
+    #[arg(short, long, overrides_with = "quiet")]
+    verbose: bool,
+}
+
+/// Parses a size that may be negative, meaning "all but the last N".
+fn parse_count(value: &str) -> Result<Count, String> {
+    let (negative, size) = match value.strip_prefix('-') {
+        Some(size) => (true, size),
+        None => (false, value),
+    };
+    Ok(Count { negative, size: shared_utils::parse_size_arg(size)? })
 }
 
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -52,20 +107,96 @@ fn print_file(mut file: Box<dyn BufRead>, mode: &Mode) -> MyResult<()> {
             }
         },
         Mode::Bytes(bytes) => {
-            let mut buf: Vec<u8> = vec![0; *bytes];            
-            let bytes = file.read(&mut buf)?;
-            let string = String::from_utf8_lossy(&buf[..bytes]);
-            print!("{string}");
+            io::copy(&mut file.by_ref().take(*bytes as u64), &mut io::stdout().lock())?;
         }
+        Mode::Chars(chars) => print_chars(file, *chars)?,
+        Mode::AllButLastBytes(bytes) => print_all_but_last_bytes(file, *bytes)?,
+        Mode::AllButLastLines(lines) => print_all_but_last_lines(file, *lines)?,
     };
     Ok(())
 }
 
+/// Prints the first `count` characters. Invalid UTF-8 sequences are passed through
+/// unchanged, every byte that doesn't continue a sequence is counted as a character.
+fn print_chars(mut file: Box<dyn BufRead>, count: usize) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    let mut seen = 0usize;
+    loop {
+        let buf = file.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let mut end = buf.len();
+        let mut done = false;
+        for (index, byte) in buf.iter().enumerate() {
+            let is_continuation = byte & 0b1100_0000 == 0b1000_0000;
+            if is_continuation {
+                continue;
+            }
+            if seen == count {
+                end = index;
+                done = true;
+                break;
+            }
+            seen += 1;
+        }
+        stdout.write_all(&buf[..end])?;
+        file.consume(end);
+        if done {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Prints everything except the last `count` bytes,
+/// holding back no more than `count` bytes at a time.
+fn print_all_but_last_bytes(mut file: Box<dyn BufRead>, count: usize) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    let mut held: VecDeque<u8> = VecDeque::new();
+    loop {
+        let buf = file.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let read = buf.len();
+        held.extend(buf);
+        file.consume(read);
+        let excess = held.len().saturating_sub(count);
+        let (front, back) = held.as_slices();
+        let front_len = front.len().min(excess);
+        stdout.write_all(&front[..front_len])?;
+        stdout.write_all(&back[..excess - front_len])?;
+        held.drain(..excess);
+    }
+    Ok(())
+}
+
+/// Prints everything except the last `count` lines,
+/// holding back no more than `count` lines at a time.
+fn print_all_but_last_lines(mut file: Box<dyn BufRead>, count: usize) -> MyResult<()> {
+    let mut stdout = io::stdout().lock();
+    let mut held: VecDeque<Vec<u8>> = VecDeque::new();
+    loop {
+        let mut line = Vec::new();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        held.push_back(line);
+        if held.len() > count {
+            if let Some(line) = held.pop_front() {
+                stdout.write_all(&line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn run() -> MyResult<()> {
     let args = Args::parse();
     let mode = Mode::from(&args);
     let total_files = args.files.len();
-    let print_names = total_files > 1;
+    let print_names = args.verbose || (!args.quiet && total_files > 1);
     for (num, filename) in args.files.iter().enumerate() {
         if print_names {
             println!("==> {filename} <==");
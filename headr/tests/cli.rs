@@ -44,6 +44,18 @@ fn dies_when_not_number_bytes() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn dies_when_both_chars_and_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-", "--chars", "10", "-c", "10"])
+        .assert()
+        .failure()
+        .stderr(str::contains(
+            "error: the argument '--chars <CHARS>' cannot be used with '--bytes <BYTES>'",
+        ));
+    Ok(())
+}
+
 #[test]
 fn dies_when_both_bytes_and_lines() -> TestResult {
     Command::cargo_bin(PRG)?
@@ -92,10 +104,32 @@ fn dies_when_both_bytes_and_lines() -> TestResult {
 #[test_case(vec!["empty.txt", "one.txt", "two.txt", "three.txt", "ten.txt"], vec!["-c", "4"], "all.c4.out"; "all bytes 4")]
 #[test_case(vec!["empty.txt", "one.txt", "two.txt", "three.txt", "ten.txt"], vec!["-n", "2"], "all.n2.out"; "all lines 2")]
 #[test_case(vec!["empty.txt", "one.txt", "two.txt", "three.txt", "ten.txt"], vec!["-n", "4"], "all.n4.out"; "all lines 4")]
+#[test_case(vec!["empty.txt"], vec!["-n", "-2"], "empty.txt.n-2.out"; "empty all but last lines 2")]
+#[test_case(vec!["empty.txt"], vec!["-c", "-4"], "empty.txt.c-4.out"; "empty all but last bytes 4")]
+#[test_case(vec!["one.txt"], vec!["-n", "-2"], "one.txt.n-2.out"; "one all but last lines 2")]
+#[test_case(vec!["one.txt"], vec!["-c", "-4"], "one.txt.c-4.out"; "one all but last bytes 4")]
+#[test_case(vec!["two.txt"], vec!["-n", "-2"], "two.txt.n-2.out"; "two all but last lines 2")]
+#[test_case(vec!["two.txt"], vec!["-c", "-4"], "two.txt.c-4.out"; "two all but last bytes 4")]
+#[test_case(vec!["three.txt"], vec!["-n", "-2"], "three.txt.n-2.out"; "three all but last lines 2")]
+#[test_case(vec!["three.txt"], vec!["-c", "-4"], "three.txt.c-4.out"; "three all but last bytes 4")]
+#[test_case(vec!["ten.txt"], vec!["-n", "-2"], "ten.txt.n-2.out"; "ten all but last lines 2")]
+#[test_case(vec!["ten.txt"], vec!["-c", "-4"], "ten.txt.c-4.out"; "ten all but last bytes 4")]
+#[test_case(vec!["ten.txt"], vec!["-n", "-1"], "ten.txt.n-1.out"; "ten all but last lines 1")]
+#[test_case(vec!["ten.txt"], vec!["-n", "-20"], "ten.txt.n-20.out"; "ten all but last lines 20")]
+#[test_case(vec!["one.txt"], vec!["-n", "-0"], "one.txt.n-0.out"; "one all but last lines 0")]
+#[test_case(vec!["one.txt"], vec!["-c", "-0"], "one.txt.c-0.out"; "one all but last bytes 0")]
+#[test_case(vec!["three.txt"], vec!["-n", "-0"], "three.txt.n-0.out"; "three all but last lines 0")]
+#[test_case(vec!["three.txt"], vec!["-c", "-0"], "three.txt.c-0.out"; "three all but last bytes 0")]
+#[test_case(vec!["ten.txt"], vec!["-n", "-0"], "ten.txt.n-0.out"; "ten all but last lines 0")]
+#[test_case(vec!["ten.txt"], vec!["-c", "-0"], "ten.txt.c-0.out"; "ten all but last bytes 0")]
+#[test_case(vec!["one.txt"], vec!["--chars", "1"], "one.txt.chars1.out"; "one chars 1")]
+#[test_case(vec!["one.txt"], vec!["--chars", "4"], "one.txt.chars4.out"; "one chars 4")]
+#[test_case(vec!["one.txt"], vec!["--chars", "200"], "one.txt.chars200.out"; "one chars 200")]
+#[test_case(vec!["binary.bin"], vec!["-c", "5"], "binary.bin.c5.out"; "binary bytes 5")]
+#[test_case(vec!["binary.bin"], vec!["--chars", "5"], "binary.bin.chars5.out"; "binary chars 5")]
 fn run(files: Vec<&str>, args: Vec<&str>, expected_file: &str) -> TestResult {
     let expected_file = expected!(expected_file);
     let expected = fs::read(&expected_file)?;
-    let expected = String::from_utf8_lossy(&expected).into_owned();
 
     let files: Vec<PathBuf> = files.iter().map(|x| input!(x)).collect();
     Command::cargo_bin(PRG)?
@@ -137,10 +171,32 @@ fn run(files: Vec<&str>, args: Vec<&str>, expected_file: &str) -> TestResult {
 #[test_case("ten.txt", vec!["-c", "4"], "ten.txt.c4.out"; "ten bytes 4")]
 #[test_case("ten.txt", vec!["-n", "2"], "ten.txt.n2.out"; "ten lines 2")]
 #[test_case("ten.txt", vec!["-n", "4"], "ten.txt.n4.out"; "ten lines 4")]
+#[test_case("empty.txt", vec!["-n", "-2"], "empty.txt.n-2.out"; "empty all but last lines 2")]
+#[test_case("empty.txt", vec!["-c", "-4"], "empty.txt.c-4.out"; "empty all but last bytes 4")]
+#[test_case("one.txt", vec!["-n", "-2"], "one.txt.n-2.out"; "one all but last lines 2")]
+#[test_case("one.txt", vec!["-c", "-4"], "one.txt.c-4.out"; "one all but last bytes 4")]
+#[test_case("two.txt", vec!["-n", "-2"], "two.txt.n-2.out"; "two all but last lines 2")]
+#[test_case("two.txt", vec!["-c", "-4"], "two.txt.c-4.out"; "two all but last bytes 4")]
+#[test_case("three.txt", vec!["-n", "-2"], "three.txt.n-2.out"; "three all but last lines 2")]
+#[test_case("three.txt", vec!["-c", "-4"], "three.txt.c-4.out"; "three all but last bytes 4")]
+#[test_case("ten.txt", vec!["-n", "-2"], "ten.txt.n-2.out"; "ten all but last lines 2")]
+#[test_case("ten.txt", vec!["-c", "-4"], "ten.txt.c-4.out"; "ten all but last bytes 4")]
+#[test_case("ten.txt", vec!["-n", "-1"], "ten.txt.n-1.out"; "ten all but last lines 1")]
+#[test_case("ten.txt", vec!["-n", "-20"], "ten.txt.n-20.out"; "ten all but last lines 20")]
+#[test_case("one.txt", vec!["-n", "-0"], "one.txt.n-0.out"; "one all but last lines 0")]
+#[test_case("one.txt", vec!["-c", "-0"], "one.txt.c-0.out"; "one all but last bytes 0")]
+#[test_case("three.txt", vec!["-n", "-0"], "three.txt.n-0.out"; "three all but last lines 0")]
+#[test_case("three.txt", vec!["-c", "-0"], "three.txt.c-0.out"; "three all but last bytes 0")]
+#[test_case("ten.txt", vec!["-n", "-0"], "ten.txt.n-0.out"; "ten all but last lines 0")]
+#[test_case("ten.txt", vec!["-c", "-0"], "ten.txt.c-0.out"; "ten all but last bytes 0")]
+#[test_case("one.txt", vec!["--chars", "1"], "one.txt.chars1.out"; "one chars 1")]
+#[test_case("one.txt", vec!["--chars", "4"], "one.txt.chars4.out"; "one chars 4")]
+#[test_case("one.txt", vec!["--chars", "200"], "one.txt.chars200.out"; "one chars 200")]
+#[test_case("binary.bin", vec!["-c", "5"], "binary.bin.c5.out"; "binary bytes 5")]
+#[test_case("binary.bin", vec!["--chars", "5"], "binary.bin.chars5.out"; "binary chars 5")]
 fn run_stdio(file: &str, args: Vec<&str>, expected_file: &str) -> TestResult {
     let expected_file = expected!(expected_file);
     let expected = fs::read(&expected_file)?;
-    let expected = String::from_utf8_lossy(&expected).into_owned();
 
     let file = input!(file);
     Command::cargo_bin(PRG)?
@@ -152,3 +208,73 @@ fn run_stdio(file: &str, args: Vec<&str>, expected_file: &str) -> TestResult {
         .stdout(expected);
     Ok(())
 }
+
+#[test_case(vec!["-c", "1b"], 512; "bytes 1b")]
+#[test_case(vec!["-c", "1K"], 1024; "bytes 1K")]
+#[test_case(vec!["-c", "1KiB"], 1024; "bytes 1KiB")]
+#[test_case(vec!["-c", "1KB"], 1000; "bytes 1KB")]
+#[test_case(vec!["-c", "1M"], 5000; "bytes 1M")]
+#[test_case(vec!["-c", "-4KB"], 1000; "all but last bytes 4KB")]
+#[test_case(vec!["--chars", "2K"], 2048; "chars 2K")]
+#[test_case(vec!["-n", "1K"], 2048; "lines 1K")]
+#[test_case(vec!["-n", "-1K"], 5000 - 2048; "all but last lines 1K")]
+fn run_size_suffix(args: Vec<&str>, expected_len: usize) -> TestResult {
+    let input = "x\n".repeat(2500);
+    Command::cargo_bin(PRG)?
+        .arg("-")
+        .args(args)
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input[..expected_len].to_string());
+    Ok(())
+}
+
+#[test]
+fn dies_when_bad_size_suffix() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-", "-c", "1X"])
+        .assert()
+        .failure()
+        .stderr(str::contains("error: invalid value '1X' for '--bytes <BYTES>'"));
+    Ok(())
+}
+
+#[test]
+fn verbose_prints_header_for_single_file() -> TestResult {
+    let file = input!("one.txt");
+    let expected = format!("==> {} <==\n{}", file.display(), fs::read_to_string(&file)?);
+    Command::cargo_bin(PRG)?
+        .arg("-v")
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test_case("-q"; "quiet")]
+#[test_case("--silent"; "silent")]
+fn quiet_prints_no_headers(flag: &str) -> TestResult {
+    let files = [input!("one.txt"), input!("two.txt")];
+    let expected = fs::read_to_string(&files[0])? + &fs::read_to_string(&files[1])?;
+    Command::cargo_bin(PRG)?
+        .arg(flag)
+        .args(files)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn last_header_switch_wins() -> TestResult {
+    let file = input!("one.txt");
+    Command::cargo_bin(PRG)?
+        .args(["-v", "-q"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(&file)?);
+    Ok(())
+}
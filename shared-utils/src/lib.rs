@@ -13,3 +13,87 @@ pub fn open(filename: &Option<&str>) -> MyResult<Box<dyn BufRead>> {
         ))),
     }
 }
+
+/// Multipliers of the size suffixes, following the GNU coreutils grammar:
+/// `K`, `M`, `G`... are powers of 1024 (same as `KiB`, `MiB`, `GiB`...),
+/// `KB`, `MB`, `GB`... are powers of 1000 and `b` means 512-byte blocks.
+const SIZE_SUFFIXES: [(&str, u64); 21] = [
+    ("b", 512),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+    ("PiB", 1 << 50),
+    ("EiB", 1 << 60),
+    ("kB", 1_000),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("PB", 1_000_000_000_000_000),
+    ("EB", 1_000_000_000_000_000_000),
+    ("k", 1 << 10),
+    ("K", 1 << 10),
+    ("M", 1 << 20),
+    ("G", 1 << 30),
+    ("T", 1 << 40),
+    ("P", 1 << 50),
+    ("E", 1 << 60),
+];
+
+/// Parses a size such as `10`, `4K`, `1MiB` or `2GB`.
+pub fn parse_size(value: &str) -> MyResult<u64> {
+    let (number, multiplier) = SIZE_SUFFIXES
+        .iter()
+        .find_map(|(suffix, multiplier)| {
+            value
+                .strip_suffix(suffix)
+                .map(|number| (number, *multiplier))
+        })
+        .unwrap_or((value, 1));
+    let number: u64 = number.parse()?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("value too large: {value}").into())
+}
+
+/// Parses a size into a `usize`, to be used as a clap `value_parser`.
+pub fn parse_size_arg(value: &str) -> Result<usize, String> {
+    let size = parse_size(value).map_err(|e| e.to_string())?;
+    usize::try_from(size).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("10").unwrap(), 10);
+        assert_eq!(parse_size("2b").unwrap(), 1024);
+        assert_eq!(parse_size("1K").unwrap(), 1024);
+        assert_eq!(parse_size("1k").unwrap(), 1024);
+        assert_eq!(parse_size("1KiB").unwrap(), 1024);
+        assert_eq!(parse_size("1KB").unwrap(), 1000);
+        assert_eq!(parse_size("1kB").unwrap(), 1000);
+        assert_eq!(parse_size("3M").unwrap(), 3 * 1024 * 1024);
+        assert_eq!(parse_size("3MiB").unwrap(), 3 * 1024 * 1024);
+        assert_eq!(parse_size("3MB").unwrap(), 3_000_000);
+        assert_eq!(parse_size("1G").unwrap(), 1 << 30);
+        assert_eq!(parse_size("1GB").unwrap(), 1_000_000_000);
+        assert_eq!(parse_size("1E").unwrap(), 1 << 60);
+
+        assert!(parse_size("").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("1X").is_err());
+        assert!(parse_size("1 K").is_err());
+        assert!(parse_size("16E").is_err());
+        assert!(parse_size("1Z").is_err());
+        assert_eq!(
+            parse_size("notnum").unwrap_err().to_string(),
+            "invalid digit found in string"
+        );
+    }
+}
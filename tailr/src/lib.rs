@@ -1,6 +1,9 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    thread,
+    time::Duration,
 };
 
 use clap::Parser;
@@ -13,6 +16,14 @@ enum Mode {
     LinesFrom(usize),
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Follow {
+    /// Keep reading from the file descriptor opened at start.
+    Descriptor,
+    /// Reopen the file by its name when it is truncated, renamed or recreated.
+    Name,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct RawArgs {
@@ -20,7 +31,7 @@ struct RawArgs {
     #[arg(default_value = "-")]
     files: Vec<String>,
 
-    /// Number of bytes to print
+    /// Number of bytes to print, accepts size suffixes such as K, KB, KiB, M, MB, MiB
     #[arg(short = 'c', long, conflicts_with = "lines")]
     bytes: Option<String>,
 
@@ -31,12 +42,46 @@ struct RawArgs {
     /// Suppresses printing of headers when multiple files are being examined.
     #[arg(short, long)]
     quiet: bool,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short = 'z', long = "zero-terminated")]
+    zero_terminated: bool,
+
+    /// Line delimiter is the given byte, not newline
+    #[arg(long, conflicts_with = "zero_terminated")]
+    separator: Option<String>,
+
+    /// Output appended data as the file grows
+    #[arg(short = 'f', long)]
+    follow: bool,
+
+    /// Same as --follow, but reopens the file by name when it is rotated (unix only)
+    #[arg(short = 'F', long = "follow-name", conflicts_with = "follow")]
+    follow_name: bool,
+
+    /// With -f or -F, terminate after the process with this ID dies (unix only)
+    #[arg(long)]
+    pid: Option<u32>,
+
+    /// With -f or -F, number of seconds to sleep between checks for new data
+    #[arg(short = 's', long = "sleep-interval", default_value = "1")]
+    sleep_interval: f64,
 }
 
 struct Args {
     files: Vec<String>,
     mode: Mode,
     quiet: bool,
+    delimiter: u8,
+    follow: Option<Follow>,
+    pid: Option<u32>,
+    sleep_interval: Duration,
+}
+
+/// Parses a count with an optional sign and size suffix, such as `-5`, `+2` or `1K`.
+fn parse_count(value: &str) -> MyResult<usize> {
+    let size = shared_utils::parse_size(value.trim_start_matches(['+', '-']))?;
+    Ok(usize::try_from(size)?)
 }
 
 fn get_args() -> MyResult<Args> {
@@ -44,20 +89,14 @@ fn get_args() -> MyResult<Args> {
 
     //println!("{args:?}");
     let mode = if let Some(value) = args.bytes {
-        let count = value
-            .replace("-", "")
-            .parse()
-            .map_err(|_| format!("illegal byte count -- {value}"))?;
+        let count = parse_count(&value).map_err(|_| format!("illegal byte count -- {value}"))?;
         if value.starts_with("+") {
             Mode::BytesFrom(count)
         } else {
             Mode::Bytes(count)
         }
     } else {
-        let count = args
-            .lines
-            .replace("-", "")
-            .parse()
+        let count = parse_count(&args.lines)
             .map_err(|_| format!("illegal line count -- {}", args.lines))?;
         if args.lines.starts_with("+") {
             Mode::LinesFrom(count)
@@ -66,90 +105,379 @@ fn get_args() -> MyResult<Args> {
         }
     };
 
+    let follow = if args.follow_name {
+        Some(Follow::Name)
+    } else if args.follow {
+        Some(Follow::Descriptor)
+    } else {
+        None
+    };
+    // Only unix has the checks for a process and for the identity of a file that these need.
+    if cfg!(not(unix)) {
+        if args.pid.is_some() {
+            return Err("--pid is not supported on this platform".into());
+        }
+        if args.follow_name {
+            return Err("-F is not supported on this platform, use -f instead".into());
+        }
+    }
+
+    let delimiter = if args.zero_terminated {
+        b'\0'
+    } else if let Some(value) = args.separator {
+        match value.as_bytes() {
+            [byte] => *byte,
+            _ => return Err(format!("separator must be a single byte -- {value}").into()),
+        }
+    } else {
+        b'\n'
+    };
+
+    let sleep_interval = Duration::try_from_secs_f64(args.sleep_interval)
+        .map_err(|_| format!("invalid number of seconds: {}", args.sleep_interval))?;
+
     Ok(Args {
         files: args.files,
         mode,
         quiet: args.quiet,
+        delimiter,
+        follow,
+        pid: args.pid,
+        sleep_interval,
     })
 }
 
-fn count_lines_and_bytes(filename: &str) -> MyResult<(usize, usize)> {
-    let mut file = BufReader::new(File::open(filename)?);
-    let mut num_lines = 0usize;
-    let mut num_bytes = 0usize;
-    let mut buf = Vec::new();
-    loop {
-        let bytes_read = file.read_until(b'\n', &mut buf)?;
-        if bytes_read == 0 {
-            break;
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Finds the offset of the first of the last `count` lines ending with `delimiter`
+/// by reading the file backwards block by block, starting from its end.
+fn find_last_lines(file: &mut (impl Read + Seek), count: usize, delimiter: u8) -> MyResult<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut end = len;
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    let mut newlines = 0usize;
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK_SIZE as u64);
+        let block = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+        for (offset, &byte) in block.iter().enumerate().rev() {
+            let position = start + offset as u64;
+            // The delimiter at the very end terminates the last line, it doesn't start a new one.
+            if byte != delimiter || position + 1 == len {
+                continue;
+            }
+            newlines += 1;
+            if newlines == count {
+                return Ok(position + 1);
+            }
         }
-        num_bytes += bytes_read;
-        num_lines += 1;
+        end = start;
     }
-    Ok((num_lines, num_bytes))
+    Ok(0)
 }
 
-fn process_file(filename: &str, mode: &Mode) -> MyResult<()> {
-    let (total_lines, total_bytes) = count_lines_and_bytes(filename)?;
-    let file = File::open(filename)?;
+/// Prints the tail of the input. Regular files are returned positioned right after
+/// the printed data, so that follow mode can continue from there; stdin and other
+/// non-seekable inputs are streamed instead and can't be followed.
+fn process_file(filename: &str, mode: &Mode, delimiter: u8) -> MyResult<Option<File>> {
+    if filename == "-" {
+        process_stream(io::stdin().lock(), mode, delimiter)?;
+        return Ok(None);
+    }
+    let mut file = File::open(filename)?;
+    if !file.metadata()?.is_file() {
+        process_stream(BufReader::new(file), mode, delimiter)?;
+        return Ok(None);
+    }
     match mode {
         Mode::BytesFrom(index) => {
-            process_bytes(file, index.saturating_sub(1))?;
+            process_bytes(&mut file, index.saturating_sub(1) as u64)?;
         }
         Mode::Bytes(count) => {
-            process_bytes(file, total_bytes.saturating_sub(*count))?;
+            let len = file.metadata()?.len();
+            process_bytes(&mut file, len.saturating_sub(*count as u64))?;
         }
         Mode::LinesFrom(index) => {
-            let file = BufReader::new(file);
-            process_lines(file, *index)?;
+            process_lines(BufReader::new(&mut file), *index, delimiter)?;
         }
         Mode::Lines(count) => {
             if *count == 0 {
-                return Ok(());
+                file.seek(SeekFrom::End(0))?;
+                return Ok(Some(file));
             }
-            let file = BufReader::new(file);
-            process_lines(file, total_lines.saturating_sub(*count) + 1)?;
+            let from = find_last_lines(&mut file, *count, delimiter)?;
+            process_bytes(&mut file, from)?;
+        }
+    }
+    Ok(Some(file))
+}
+
+/// Prints the tail of an input that can only be read once, from start to end,
+/// keeping no more than the requested number of lines or bytes in memory.
+fn process_stream(mut input: impl BufRead, mode: &Mode, delimiter: u8) -> MyResult<()> {
+    match mode {
+        Mode::BytesFrom(index) => {
+            let skip = index.saturating_sub(1) as u64;
+            io::copy(&mut input.by_ref().take(skip), &mut io::sink())?;
+            let mut buf = Vec::new();
+            input.read_to_end(&mut buf)?;
+            print!("{}", String::from_utf8_lossy(&buf));
+        }
+        Mode::Bytes(count) => {
+            let mut last = VecDeque::new();
+            loop {
+                let chunk = input.fill_buf()?;
+                if chunk.is_empty() {
+                    break;
+                }
+                let chunk_len = chunk.len();
+                let chunk = &chunk[chunk_len.saturating_sub(*count)..];
+                let excess = (last.len() + chunk.len()).saturating_sub(*count);
+                last.drain(..excess);
+                last.extend(chunk);
+                input.consume(chunk_len);
+            }
+            print!("{}", String::from_utf8_lossy(last.make_contiguous()));
+        }
+        Mode::LinesFrom(index) => {
+            process_lines(input, *index, delimiter)?;
+        }
+        Mode::Lines(count) => {
+            let mut last: VecDeque<Vec<u8>> = VecDeque::new();
+            loop {
+                let mut line = Vec::new();
+                if input.read_until(delimiter, &mut line)? == 0 {
+                    break;
+                }
+                last.push_back(line);
+                if last.len() > *count {
+                    last.pop_front();
+                }
+            }
+            let buf: Vec<u8> = last.into_iter().flatten().collect();
+            print!("{}", String::from_utf8_lossy(&buf));
         }
     }
     Ok(())
 }
 
-fn process_bytes(mut file: impl Read + Seek, from: usize) -> MyResult<()> {
-    file.seek(SeekFrom::Start(from as u64))?;
+fn process_bytes(mut file: impl Read + Seek, from: u64) -> MyResult<()> {
+    file.seek(SeekFrom::Start(from))?;
     let mut buf = Vec::new();
     file.read_to_end(&mut buf)?;
     print!("{}", String::from_utf8_lossy(&buf));
     Ok(())
 }
 
-fn process_lines(mut file: impl BufRead, from: usize) -> MyResult<()> {
+fn process_lines(mut file: impl BufRead, from: usize, delimiter: u8) -> MyResult<()> {
     let mut current = 0usize;
-    let mut buf = String::new();
-    while file.read_line(&mut buf)? > 0 {
+    let mut buf = Vec::new();
+    while file.read_until(delimiter, &mut buf)? > 0 {
         current += 1;
         if current >= from {
-            print!("{buf}");
+            print!("{}", String::from_utf8_lossy(&buf));
         }
         buf.clear();
     }
     Ok(())
 }
 
+/// State of a single file watched in follow mode.
+struct Followed<'a> {
+    name: &'a str,
+    file: Option<File>,
+    position: u64,
+}
+
+impl Followed<'_> {
+    /// Reads everything appended since the last check.
+    fn read_new_data(&mut self, buf: &mut Vec<u8>) -> MyResult<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.position {
+            eprintln!("tailr: {}: file truncated", self.name);
+            self.position = 0;
+        }
+        file.seek(SeekFrom::Start(self.position))?;
+        let bytes_read = file.read_to_end(buf)?;
+        self.position += bytes_read as u64;
+        Ok(())
+    }
+
+    /// Reopens the file if the name now points to a different file than the one being read.
+    /// Returns `true` when the file was reopened.
+    fn check_name(&mut self) -> bool {
+        match fs::metadata(self.name) {
+            Ok(metadata) => {
+                let replaced = match &self.file {
+                    None => true,
+                    Some(file) => match file.metadata() {
+                        Ok(current) => !is_same_file(&current, &metadata),
+                        Err(_) => true,
+                    },
+                };
+                if !replaced {
+                    return false;
+                }
+                if let Ok(file) = File::open(self.name) {
+                    if self.file.is_some() {
+                        eprintln!(
+                            "tailr: '{}' has been replaced;  following new file",
+                            self.name
+                        );
+                    } else {
+                        eprintln!("tailr: '{}' has appeared;  following new file", self.name);
+                    }
+                    self.file = Some(file);
+                    self.position = 0;
+                    return true;
+                }
+                false
+            }
+            Err(_) => {
+                if self.file.take().is_some() {
+                    eprintln!(
+                        "tailr: '{}' has become inaccessible: No such file or directory",
+                        self.name
+                    );
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(left: &fs::Metadata, right: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    left.dev() == right.dev() && left.ino() == right.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_left: &fs::Metadata, _right: &fs::Metadata) -> bool {
+    unreachable!("-F is rejected by get_args on this platform")
+}
+
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    // Signal 0 performs the error checking only, without sending anything.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    unreachable!("--pid is rejected by get_args on this platform")
+}
+
+fn print_new_data(
+    followed: &mut Followed,
+    num: usize,
+    buf: &mut Vec<u8>,
+    print_names: bool,
+    last_printed: &mut Option<usize>,
+) -> MyResult<()> {
+    buf.clear();
+    if let Err(error) = followed.read_new_data(buf) {
+        eprintln!("tailr: {}: {error}", followed.name);
+        followed.file = None;
+    }
+    if buf.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = io::stdout().lock();
+    if print_names && *last_printed != Some(num) {
+        writeln!(stdout, "\n==> {} <==", followed.name)?;
+    }
+    *last_printed = Some(num);
+    stdout.write_all(buf)?;
+    Ok(())
+}
+
+fn follow_files(
+    files: &mut [Followed],
+    follow: Follow,
+    args: &Args,
+    print_names: bool,
+    mut last_printed: Option<usize>,
+) -> MyResult<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    let mut buf = Vec::new();
+    loop {
+        // Checking before reading guarantees that the data written by the process
+        // right before its exit is still printed.
+        let process_alive = args.pid.is_none_or(is_process_alive);
+
+        for (num, followed) in files.iter_mut().enumerate() {
+            print_new_data(followed, num, &mut buf, print_names, &mut last_printed)?;
+            // The rest of a rotated file is printed before switching to the new one.
+            if follow == Follow::Name && followed.check_name() {
+                print_new_data(followed, num, &mut buf, print_names, &mut last_printed)?;
+            }
+        }
+        stdout.flush()?;
+
+        if !process_alive {
+            return Ok(());
+        }
+        if follow == Follow::Descriptor && files.iter().all(|f| f.file.is_none()) {
+            return Err("no files remaining".into());
+        }
+        thread::sleep(args.sleep_interval);
+    }
+}
+
 pub fn run() -> MyResult<()> {
     let args = get_args()?;
     let total_files = args.files.len();
     let print_names = !args.quiet && total_files > 1;
+    let mut followed = Vec::with_capacity(total_files);
+    let mut last_printed = None;
     for (num, filename) in args.files.iter().enumerate() {
         if print_names {
             println!("==> {filename} <==");
         }
-        if let Err(error) = process_file(&filename, &args.mode) {
-            eprintln!("{filename}: {error}");
-        }
+        // Streamed inputs are consumed by now and can't be followed, while missing
+        // files still are, as they can appear later when followed by name.
+        let (file, follows) = match process_file(filename, &args.mode, args.delimiter) {
+            Ok(file) => {
+                let follows = file.is_some();
+                (file, follows)
+            }
+            Err(error) => {
+                eprintln!("{filename}: {error}");
+                (None, true)
+            }
+        };
+        let follows = follows && args.follow.is_some();
         let is_last = num + 1 == total_files;
         if print_names && !is_last {
             println!();
         }
+        if follows {
+            let position = match file.as_ref().map(|mut file| file.stream_position()) {
+                Some(position) => position?,
+                None => 0,
+            };
+            followed.push(Followed {
+                name: filename,
+                file,
+                position,
+            });
+        }
+        if is_last {
+            last_printed = follows.then(|| followed.len() - 1);
+        }
+    }
+    if let Some(follow) = args.follow {
+        io::stdout().flush()?;
+        follow_files(&mut followed, follow, &args, print_names, last_printed)?;
     }
     Ok(())
 }
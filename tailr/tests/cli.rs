@@ -1,8 +1,13 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
+use std::fs::OpenOptions;
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::Duration;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -12,6 +17,8 @@ const ONE: &str = "tests/inputs/one.txt";
 const TWO: &str = "tests/inputs/two.txt";
 const THREE: &str = "tests/inputs/three.txt";
 const TEN: &str = "tests/inputs/ten.txt";
+const RECORDS: &str = "tests/inputs/records.txt";
+const SEMICOLONS: &str = "tests/inputs/semicolons.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -817,3 +824,306 @@ fn multiple_files_c_plus_3() -> TestResult {
         "tests/expected/all.c+3.out",
     )
 }
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> TestResult {
+    let mut file = File::open(expected_file)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    let expected = String::from_utf8_lossy(&buffer);
+
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .pipe_stdin(input_file)?
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
+
+    Ok(())
+}
+
+#[test]
+fn stdin_default() -> TestResult {
+    run_stdin(&[], TEN, "tests/expected/ten.txt.out")
+}
+
+#[test]
+fn stdin_n0() -> TestResult {
+    run_stdin(&["-n", "0"], TEN, "tests/expected/ten.txt.n0.out")
+}
+
+#[test]
+fn stdin_n3() -> TestResult {
+    run_stdin(&["-n", "3"], TEN, "tests/expected/ten.txt.n3.out")
+}
+
+#[test]
+fn stdin_n200() -> TestResult {
+    run_stdin(&["-n", "200"], TEN, "tests/expected/ten.txt.n200.out")
+}
+
+#[test]
+fn stdin_n_plus_2() -> TestResult {
+    run_stdin(&["-n", "+2"], TEN, "tests/expected/ten.txt.n+2.out")
+}
+
+#[test]
+fn stdin_c8() -> TestResult {
+    run_stdin(&["-c", "8"], THREE, "tests/expected/three.txt.c8.out")
+}
+
+#[test]
+fn stdin_c200() -> TestResult {
+    run_stdin(&["-c", "200"], THREE, "tests/expected/three.txt.c200.out")
+}
+
+#[test]
+fn stdin_c_plus_2() -> TestResult {
+    run_stdin(&["-c", "+2"], THREE, "tests/expected/three.txt.c+2.out")
+}
+
+#[test]
+fn stdin_dash_between_files() -> TestResult {
+    run_stdin(
+        &["-n", "3", TEN, "-", ONE],
+        ONE,
+        "tests/expected/stdin_between.n3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_separator() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--separator", "ab", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "separator must be a single byte -- ab",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn records_z_n1() -> TestResult {
+    run(
+        &["-z", "-n", "1", RECORDS],
+        "tests/expected/records.txt.z.n1.out",
+    )
+}
+
+#[test]
+fn records_z_n2() -> TestResult {
+    run(
+        &["-z", "-n", "2", RECORDS],
+        "tests/expected/records.txt.z.n2.out",
+    )
+}
+
+#[test]
+fn records_z_n_plus_2() -> TestResult {
+    run(
+        &["-z", "-n", "+2", RECORDS],
+        "tests/expected/records.txt.z.n+2.out",
+    )
+}
+
+#[test]
+fn records_z_n2_stdin() -> TestResult {
+    run_stdin(
+        &["-z", "-n", "2"],
+        RECORDS,
+        "tests/expected/records.txt.z.n2.out",
+    )
+}
+
+#[test]
+fn separator_n2() -> TestResult {
+    run(
+        &["--separator", ";", "-n", "2", SEMICOLONS],
+        "tests/expected/semicolons.txt.sep.n2.out",
+    )
+}
+
+#[test]
+fn separator_n2_stdin() -> TestResult {
+    run_stdin(
+        &["--separator", ";", "-n", "2"],
+        SEMICOLONS,
+        "tests/expected/semicolons.txt.sep.n2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_across_blocks() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("big.txt");
+    let lines: Vec<String> = (1..=20000).map(|num| format!("line {num}\n")).collect();
+    fs::write(&path, lines.concat())?;
+    let name = path.to_str().unwrap();
+
+    for count in [1, 3, 15000, 20000, 30000] {
+        let expected = lines[lines.len().saturating_sub(count)..].concat();
+        Command::cargo_bin(PRG)?
+            .args(["-n", &count.to_string(), name])
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_suffixes() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("big.txt");
+    let content = "x\n".repeat(5000);
+    fs::write(&path, &content)?;
+    let name = path.to_str().unwrap();
+
+    for (args, expected) in [
+        (["-c", "1K"], &content[content.len() - 1024..]),
+        (["-c", "1KB"], &content[content.len() - 1000..]),
+        (["-c", "+1b"], &content[511..]),
+        (["-n", "1K"], &content[content.len() - 2048..]),
+        (["-n", "+4K"], &content[2 * 4095..]),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .arg(name)
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    Ok(())
+}
+
+// --------------------------------------------------
+// Runs tailr in follow mode until the helper process exits,
+// calling `update` while tailr is watching the files.
+fn run_follow(
+    args: &[&str],
+    update: impl FnOnce() -> TestResult,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut watched = std::process::Command::new("sleep").arg("2").spawn()?;
+    let pid = watched.id().to_string();
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(args)
+        .args(["-s", "0.1", "--pid", &pid])
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    thread::sleep(Duration::from_millis(500));
+    update()?;
+    watched.wait()?;
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_appended_data() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\ntwo\n")?;
+    let name = path.to_str().unwrap();
+
+    let output = run_follow(&["-f", "-n", "1", name], || {
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(b"three\nfour\n")?;
+        Ok(())
+    })?;
+    assert_eq!(output, "two\nthree\nfour\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_truncated_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\ntwo\n")?;
+    let name = path.to_str().unwrap();
+
+    let output = run_follow(&["-f", name], || {
+        fs::write(&path, "new\n")?;
+        Ok(())
+    })?;
+    assert_eq!(output, "one\ntwo\nnew\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_name_rotated_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\n")?;
+    let name = path.to_str().unwrap();
+
+    let output = run_follow(&["-F", name], || {
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(b"two\n")?;
+        fs::rename(&path, dir.path().join("log.txt.1"))?;
+        fs::write(&path, "three\n")?;
+        Ok(())
+    })?;
+    assert_eq!(output, "one\ntwo\nthree\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_descriptor_ignores_rotation() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log.txt");
+    fs::write(&path, "one\n")?;
+    let name = path.to_str().unwrap();
+
+    let output = run_follow(&["-f", name], || {
+        let rotated = dir.path().join("log.txt.1");
+        fs::rename(&path, &rotated)?;
+        fs::write(&path, "new\n")?;
+        let mut file = OpenOptions::new().append(true).open(&rotated)?;
+        file.write_all(b"two\n")?;
+        Ok(())
+    })?;
+    assert_eq!(output, "one\ntwo\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_multiple_files() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    fs::write(&first, "1\n")?;
+    fs::write(&second, "2\n")?;
+    let first_name = first.to_str().unwrap();
+    let second_name = second.to_str().unwrap();
+
+    let output = run_follow(&["-f", first_name, second_name], || {
+        let mut file = OpenOptions::new().append(true).open(&second)?;
+        file.write_all(b"22\n")?;
+        thread::sleep(Duration::from_millis(300));
+        let mut file = OpenOptions::new().append(true).open(&first)?;
+        file.write_all(b"11\n")?;
+        Ok(())
+    })?;
+    let expected = format!(
+        "==> {first_name} <==\n1\n\n==> {second_name} <==\n2\n22\n\n==> {first_name} <==\n11\n"
+    );
+    assert_eq!(output, expected);
+
+    Ok(())
+}
@@ -1,8 +1,13 @@
-use std::io::{BufRead, BufWriter, Write, self};
-use std::fs::{ File, self };
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write, self};
+use std::fs::{ File, OpenOptions };
 
-use shared_utils::MyResult;
-use clap::Parser;
+use shared_utils::{parse_size_arg, MyResult};
+use clap::{Parser, ValueEnum};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -13,61 +18,492 @@ struct Args {
     /// A file to write the results to
     output_file: Option<String>,
 
+    #[arg(long)]
+    /// Append to the output file instead of overwriting it
+    append: bool,
+
+    #[arg(short = 'z', long)]
+    /// Line delimiter is NUL, not newline
+    zero_terminated: bool,
+
     #[arg(short, long)]
     /// Prefix lines by the number of occurrences
     count: bool,
+
+    #[arg(short = 'd', long)]
+    /// Only print duplicate lines, one for each group
+    repeated: bool,
+
+    #[arg(short = 'u', long)]
+    /// Only print unique lines
+    unique: bool,
+
+    #[arg(short = 'i', long)]
+    /// Ignore differences in case when comparing
+    ignore_case: bool,
+
+    #[arg(short = 'f', long, value_name = "N", default_value_t = 0)]
+    /// Avoid comparing the first N fields
+    skip_fields: usize,
+
+    #[arg(short = 's', long, value_name = "N", default_value_t = 0)]
+    /// Avoid comparing the first N characters
+    skip_chars: usize,
+
+    #[arg(short = 'w', long, value_name = "N")]
+    /// Compare no more than N characters in lines
+    check_chars: Option<usize>,
+
+    #[arg(
+        short = 'D',
+        long,
+        value_enum,
+        value_name = "METHOD",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "none"
+    )]
+    /// Print all duplicate lines, groups can be delimited with an empty line
+    all_repeated: Option<AllRepeated>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "METHOD",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "separate",
+        conflicts_with_all = ["repeated", "unique", "all_repeated"]
+    )]
+    /// Show all lines, separating groups with an empty line
+    group: Option<Group>,
+
+    #[arg(long, conflicts_with_all = ["all_repeated", "group"])]
+    /// Find duplicates in the whole input, not only among adjacent lines
+    global: bool,
+
+    #[arg(long, value_name = "SIZE", requires = "global", value_parser = parse_size_arg)]
+    /// With --global, keep up to SIZE of distinct lines in memory before spilling them to disk
+    max_memory: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum AllRepeated {
+    /// Do not delimit groups
+    None,
+    /// Output an empty line before each group
+    Prepend,
+    /// Output an empty line between groups
+    Separate,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Group {
+    /// Output an empty line between groups
+    Separate,
+    /// Output an empty line before each group
+    Prepend,
+    /// Output an empty line after each group
+    Append,
+    /// Output an empty line before and after each group
+    Both,
+}
+
+/// Where empty lines are printed around the groups of identical lines,
+/// when every line of a group is printed rather than only the first one.
+#[derive(Default)]
+struct Delimiters {
+    before_first: bool,
+    between: bool,
+    after_last: bool,
+}
+
+impl From<AllRepeated> for Delimiters {
+    fn from(value: AllRepeated) -> Self {
+        match value {
+            AllRepeated::None => Self::default(),
+            AllRepeated::Prepend => Self { before_first: true, between: true, after_last: false },
+            AllRepeated::Separate => Self { before_first: false, between: true, after_last: false },
+        }
+    }
+}
+
+impl From<Group> for Delimiters {
+    fn from(value: Group) -> Self {
+        match value {
+            Group::Separate => Self { before_first: false, between: true, after_last: false },
+            Group::Prepend => Self { before_first: true, between: true, after_last: false },
+            Group::Append => Self { before_first: false, between: true, after_last: true },
+            Group::Both => Self { before_first: true, between: true, after_last: true },
+        }
+    }
+}
+
+/// Decides which part of a line is compared when looking for duplicates.
+struct Comparison {
+    ignore_case: bool,
+    skip_fields: usize,
+    skip_chars: usize,
+    check_chars: Option<usize>,
+}
+
+impl From<&Args> for Comparison {
+    fn from(args: &Args) -> Self {
+        Self {
+            ignore_case: args.ignore_case,
+            skip_fields: args.skip_fields,
+            skip_chars: args.skip_chars,
+            check_chars: args.check_chars,
+        }
+    }
 }
 
-fn write_to_output(line: &str, writer: &mut BufWriter<Box<dyn Write>>, print_count: bool, count: usize) -> MyResult<()> {
+impl Comparison {
+    /// Returns the part of the line that is compared. Fields are runs of blanks
+    /// followed by non-blank characters, they are skipped before the characters.
+    fn key<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let is_blank = |c: char| c == ' ' || c == '\t';
+        let mut key = line;
+        for _ in 0..self.skip_fields {
+            key = key.trim_start_matches(is_blank);
+            key = key.trim_start_matches(|c| !is_blank(c));
+        }
+        key = skip_chars(key, self.skip_chars);
+        if let Some(check_chars) = self.check_chars {
+            let rest = skip_chars(key, check_chars);
+            key = &key[..key.len() - rest.len()];
+        }
+        if self.ignore_case {
+            Cow::Owned(key.to_lowercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+
+    fn is_same(&self, left: &str, right: &str) -> bool {
+        self.key(left) == self.key(right)
+    }
+}
+
+fn skip_chars(line: &str, count: usize) -> &str {
+    match line.char_indices().nth(count) {
+        Some((index, _)) => &line[index..],
+        None => "",
+    }
+}
+
+/// Decides which groups of identical lines are printed.
+struct Selection {
+    repeated: bool,
+    unique: bool,
+}
+
+impl From<&Args> for Selection {
+    fn from(args: &Args) -> Self {
+        Self {
+            repeated: args.repeated || args.all_repeated.is_some(),
+            unique: args.unique,
+        }
+    }
+}
+
+impl Selection {
+    fn is_selected(&self, count: usize) -> bool {
+        (count > 1 || !self.repeated) && (count == 1 || !self.unique)
+    }
+}
+
+fn write_to_output(line: &str, writer: &mut BufWriter<Box<dyn Write>>, print_count: bool, count: usize, delimiter: u8) -> MyResult<()> {
     if print_count {
-        writeln!(writer, "{:>7} {}", count, line)?;
+        write!(writer, "{:>7} {}", count, line)?;
     } else {
-        writeln!(writer, "{line}")?;
+        write!(writer, "{line}")?;
     }
+    writer.write_all(&[delimiter])?;
     Ok(())
 }
 
-fn write_lines(input: Box<dyn BufRead>, output: &mut BufWriter<Box<dyn Write>>, print_count: bool) -> MyResult<()> {
-    let mut lines = input.lines();
-    let prev_line = lines.next();
-    if prev_line.is_none() {
+/// Reads the next line without its delimiter, or `None` at the end of input.
+fn read_line(input: &mut dyn BufRead, delimiter: u8) -> MyResult<Option<String>> {
+    let mut buf = Vec::new();
+    if input.read_until(delimiter, &mut buf)? == 0 {
+        return Ok(None);
+    }
+    if buf.last() == Some(&delimiter) {
+        buf.pop();
+    }
+    Ok(Some(String::from_utf8(buf)?))
+}
+
+struct Options {
+    print_count: bool,
+    comparison: Comparison,
+    selection: Selection,
+    /// Print every line of each group, delimited this way, instead of only the first one
+    all_lines: Option<Delimiters>,
+    /// Byte that terminates the lines, both in input and output
+    delimiter: u8,
+}
+
+/// Lines that are identical to the first one, buffered until the group ends.
+/// Only the first line is kept unless all the lines of the group are printed.
+struct LineGroup {
+    lines: Vec<String>,
+    count: usize,
+}
+
+impl LineGroup {
+    fn new(line: String) -> Self {
+        Self { lines: vec![line], count: 1 }
+    }
+}
+
+fn write_group(group: &LineGroup, writer: &mut BufWriter<Box<dyn Write>>, options: &Options, printed_any: &mut bool) -> MyResult<()> {
+    if !options.selection.is_selected(group.count) {
         return Ok(());
     }
-    let mut prev_line = prev_line.unwrap()?;
-    let mut count: usize = 1;
-    for line in lines {
-        let line = line?;
-        if line == prev_line {
-            count += 1;
+    match &options.all_lines {
+        None => write_to_output(&group.lines[0], writer, options.print_count, group.count, options.delimiter)?,
+        Some(delimiters) => {
+            if (*printed_any && delimiters.between) || (!*printed_any && delimiters.before_first) {
+                writer.write_all(&[options.delimiter])?;
+            }
+            for line in &group.lines {
+                write_to_output(line, writer, options.print_count, group.count, options.delimiter)?;
+            }
+        }
+    }
+    *printed_any = true;
+    Ok(())
+}
+
+fn write_lines(mut input: Box<dyn BufRead>, output: &mut BufWriter<Box<dyn Write>>, options: &Options) -> MyResult<()> {
+    let Some(first_line) = read_line(&mut input, options.delimiter)? else {
+        return Ok(());
+    };
+    let mut group = LineGroup::new(first_line);
+    let mut printed_any = false;
+    while let Some(line) = read_line(&mut input, options.delimiter)? {
+        if options.comparison.is_same(&line, &group.lines[0]) {
+            group.count += 1;
+            if options.all_lines.is_some() {
+                group.lines.push(line);
+            }
             continue;
         }
-        write_to_output(&prev_line, output, print_count, count)?;
-        prev_line = line;
-        count = 1;
+        write_group(&group, output, options, &mut printed_any)?;
+        group = LineGroup::new(line);
     }
-    if !prev_line.is_empty() {
-        write_to_output(&prev_line, output, print_count, count)?;
+    write_group(&group, output, options, &mut printed_any)?;
+    if printed_any && options.all_lines.as_ref().is_some_and(|delimiters| delimiters.after_last) {
+        output.write_all(&[options.delimiter])?;
     }
-    
+
     Ok(())
 }
 
+/// Number of files the distinct lines are spread over when they don't fit in memory.
+const PARTITIONS: u64 = 64;
+/// Rough memory taken by a distinct line in addition to its text and key.
+const ENTRY_OVERHEAD: usize = 64;
+
+/// The first occurrence of a distinct line and the total number of its occurrences.
+struct Occurrence {
+    index: u64,
+    count: usize,
+    line: String,
+}
+
+impl Occurrence {
+    fn merge(&mut self, other: Occurrence) {
+        self.count += other.count;
+        if other.index < self.index {
+            self.index = other.index;
+            self.line = other.line;
+        }
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.index.to_le_bytes())?;
+        writer.write_all(&(self.count as u64).to_le_bytes())?;
+        writer.write_all(&(self.line.len() as u64).to_le_bytes())?;
+        writer.write_all(self.line.as_bytes())
+    }
+
+    fn read_from(reader: &mut impl Read) -> MyResult<Option<Self>> {
+        let mut buf = [0u8; 8];
+        match reader.read_exact(&mut buf) {
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let index = u64::from_le_bytes(buf);
+        reader.read_exact(&mut buf)?;
+        let count = u64::from_le_bytes(buf) as usize;
+        reader.read_exact(&mut buf)?;
+        let mut line = vec![0u8; u64::from_le_bytes(buf) as usize];
+        reader.read_exact(&mut line)?;
+        Ok(Some(Self { index, count, line: String::from_utf8(line)? }))
+    }
+}
+
+/// Files on disk that hold the occurrences once they don't fit in memory,
+/// each distinct line always goes to the same partition.
+struct Partitions {
+    dir: tempfile::TempDir,
+    writers: Vec<BufWriter<File>>,
+}
+
+impl Partitions {
+    fn new() -> MyResult<Self> {
+        let dir = tempfile::tempdir()?;
+        let writers = (0..PARTITIONS)
+            .map(|partition| Ok(BufWriter::new(File::create(dir.path().join(partition.to_string()))?)))
+            .collect::<MyResult<_>>()?;
+        Ok(Self { dir, writers })
+    }
+
+    fn write(&mut self, key: &str, occurrence: &Occurrence) -> io::Result<()> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let partition = (hasher.finish() % PARTITIONS) as usize;
+        occurrence.write_to(&mut self.writers[partition])
+    }
+
+    /// Merges the occurrences of the same lines within every partition, then merges
+    /// the partitions by the index of the first occurrence to restore the input order.
+    fn write_output(mut self, output: &mut BufWriter<Box<dyn Write>>, options: &Options) -> MyResult<()> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        let mut readers = Vec::with_capacity(self.writers.len());
+        for partition in 0..PARTITIONS {
+            let path = self.dir.path().join(partition.to_string());
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut seen: HashMap<String, Occurrence> = HashMap::new();
+            while let Some(occurrence) = Occurrence::read_from(&mut reader)? {
+                let key = options.comparison.key(&occurrence.line).into_owned();
+                match seen.entry(key) {
+                    Entry::Occupied(mut entry) => entry.get_mut().merge(occurrence),
+                    Entry::Vacant(entry) => {
+                        entry.insert(occurrence);
+                    }
+                }
+            }
+            let mut occurrences: Vec<Occurrence> = seen.into_values().collect();
+            occurrences.sort_unstable_by_key(|occurrence| occurrence.index);
+            let path = self.dir.path().join(format!("{partition}.merged"));
+            let mut writer = BufWriter::new(File::create(&path)?);
+            for occurrence in &occurrences {
+                occurrence.write_to(&mut writer)?;
+            }
+            writer.flush()?;
+            readers.push(BufReader::new(File::open(&path)?));
+        }
+
+        let mut heads = Vec::with_capacity(readers.len());
+        let mut heap = BinaryHeap::new();
+        for (partition, reader) in readers.iter_mut().enumerate() {
+            let head = Occurrence::read_from(reader)?;
+            if let Some(occurrence) = &head {
+                heap.push(Reverse((occurrence.index, partition)));
+            }
+            heads.push(head);
+        }
+        while let Some(Reverse((_, partition))) = heap.pop() {
+            if let Some(occurrence) = heads[partition].take() {
+                write_occurrence(&occurrence, output, options)?;
+            }
+            heads[partition] = Occurrence::read_from(&mut readers[partition])?;
+            if let Some(occurrence) = &heads[partition] {
+                heap.push(Reverse((occurrence.index, partition)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_occurrence(occurrence: &Occurrence, output: &mut BufWriter<Box<dyn Write>>, options: &Options) -> MyResult<()> {
+    if options.selection.is_selected(occurrence.count) {
+        write_to_output(&occurrence.line, output, options.print_count, occurrence.count, options.delimiter)?;
+    }
+    Ok(())
+}
+
+/// Writes the first occurrence of every distinct line of the whole input, in the input order.
+fn write_global_lines(mut input: Box<dyn BufRead>, output: &mut BufWriter<Box<dyn Write>>, options: &Options, max_memory: Option<usize>) -> MyResult<()> {
+    let mut seen: HashMap<String, Occurrence> = HashMap::new();
+    let mut memory = 0usize;
+    let mut partitions: Option<Partitions> = None;
+    let mut index = 0u64;
+    while let Some(line) = read_line(&mut input, options.delimiter)? {
+        let key = options.comparison.key(&line).into_owned();
+        let occurrence = Occurrence { index, count: 1, line };
+        index += 1;
+        if let Some(partitions) = partitions.as_mut() {
+            partitions.write(&key, &occurrence)?;
+            continue;
+        }
+        match seen.entry(key) {
+            Entry::Occupied(mut entry) => entry.get_mut().count += 1,
+            Entry::Vacant(entry) => {
+                memory += entry.key().len() + occurrence.line.len() + ENTRY_OVERHEAD;
+                entry.insert(occurrence);
+            }
+        }
+        if max_memory.is_some_and(|max_memory| memory > max_memory) {
+            let mut spilled = Partitions::new()?;
+            for (key, occurrence) in seen.drain() {
+                spilled.write(&key, &occurrence)?;
+            }
+            partitions = Some(spilled);
+        }
+    }
+
+    match partitions {
+        Some(partitions) => partitions.write_output(output, options),
+        None => {
+            let mut occurrences: Vec<Occurrence> = seen.into_values().collect();
+            occurrences.sort_unstable_by_key(|occurrence| occurrence.index);
+            for occurrence in &occurrences {
+                write_occurrence(occurrence, output, options)?;
+            }
+            Ok(())
+        }
+    }
+}
+
 pub fn run() -> MyResult<()> {
     let args = Args::parse();
-    let input = shared_utils::open(&args.input_file.as_ref().map(|s| s.as_str()))?;
-    let mut output: BufWriter<Box<dyn Write>> = match args.output_file.as_ref().map(|s| s.as_str()) {
+    let input = shared_utils::open(&args.input_file.as_deref())?;
+    let mut output: BufWriter<Box<dyn Write>> = match args.output_file.as_deref() {
         None | Some("-") => {
             BufWriter::new(Box::new(io::stdout()))
         },
         Some(filename) => {
-            let output_file = match fs::metadata(filename) {
-                Ok(_) => File::open(filename),
-                Err(_) => File::create(filename),
+            let output_file = if args.append {
+                OpenOptions::new().create(true).append(true).open(filename)
+            } else {
+                File::create(filename)
             }?;
             BufWriter::new(Box::new(output_file))
         }
     };
-    write_lines(input, &mut output, args.count)?;
+    let options = Options {
+        print_count: args.count,
+        comparison: Comparison::from(&args),
+        selection: Selection::from(&args),
+        all_lines: match (args.all_repeated, args.group) {
+            (Some(all_repeated), _) => Some(Delimiters::from(all_repeated)),
+            (_, Some(group)) => Some(Delimiters::from(group)),
+            _ => None,
+        },
+        delimiter: if args.zero_terminated { b'\0' } else { b'\n' },
+    };
+    if args.global {
+        write_global_lines(input, &mut output, &options, args.max_memory)?;
+    } else {
+        write_lines(input, &mut output, &options)?;
+    }
     output.flush()?;
 
     Ok(())
@@ -1,7 +1,7 @@
 use assert_cmd::Command;
 use path_macro::path;
 use predicates::str;
-use std::{fs, path::PathBuf};
+use std::fs;
 use test_case::test_case;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
@@ -30,6 +30,38 @@ fn dies_when_input_file_not_exists() -> TestResult {
     Ok(())
 }
 
+#[test_case("-d"; "repeated")]
+#[test_case("-u"; "unique")]
+#[test_case("-D"; "all repeated")]
+fn dies_when_group_with_selection(arg: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--group", arg])
+        .assert()
+        .failure()
+        .stderr(str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn dies_when_max_memory_without_global() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--max-memory", "1M"])
+        .assert()
+        .failure()
+        .stderr(str::contains("--global"));
+    Ok(())
+}
+
+#[test]
+fn dies_when_bad_max_memory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--global", "--max-memory", "1X"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid value '1X'"));
+    Ok(())
+}
+
 #[test_case("empty.txt", vec![], "empty.txt.out"; "empty")]
 #[test_case("empty.txt", vec!["-c"], "empty.txt.c.out"; "empty with counts")]
 #[test_case("one.txt", vec![], "one.txt.out"; "one")]
@@ -52,6 +84,37 @@ fn dies_when_input_file_not_exists() -> TestResult {
 #[test_case("three.txt", vec!["-c"], "three.txt.c.out"; "three with counts")]
 #[test_case("two.txt", vec![], "two.txt.out"; "two")]
 #[test_case("two.txt", vec!["-c"], "two.txt.c.out"; "two with counts")]
+#[test_case("fields.txt", vec!["-d"], "fields.txt.d.out"; "fields -d")]
+#[test_case("fields.txt", vec!["-u"], "fields.txt.u.out"; "fields -u")]
+#[test_case("fields.txt", vec!["-i"], "fields.txt.i.out"; "fields -i")]
+#[test_case("fields.txt", vec!["-f", "1"], "fields.txt.f1.out"; "fields -f 1")]
+#[test_case("fields.txt", vec!["-f", "1", "-i"], "fields.txt.f1.i.out"; "fields -f 1 -i")]
+#[test_case("fields.txt", vec!["-s", "2"], "fields.txt.s2.out"; "fields -s 2")]
+#[test_case("fields.txt", vec!["-w", "1"], "fields.txt.w1.out"; "fields -w 1")]
+#[test_case("fields.txt", vec!["-f", "1", "-w", "6"], "fields.txt.f1.w6.out"; "fields -f 1 -w 6")]
+#[test_case("fields.txt", vec!["-c", "-d"], "fields.txt.c.d.out"; "fields -c -d")]
+#[test_case("fields.txt", vec!["-c", "-u"], "fields.txt.c.u.out"; "fields -c -u")]
+#[test_case("fields.txt", vec!["-d", "-u"], "fields.txt.d.u.out"; "fields -d -u")]
+#[test_case("fields.txt", vec!["-c", "-f", "1", "-s", "1", "-w", "3", "-i"], "fields.txt.c.f1.s1.w3.i.out"; "fields -c -f 1 -s 1 -w 3 -i")]
+#[test_case("fields.txt", vec!["-f", "2"], "fields.txt.f2.out"; "fields -f 2")]
+#[test_case("fields.txt", vec!["-f", "5"], "fields.txt.f5.out"; "fields -f 5")]
+#[test_case("groups.txt", vec!["-D"], "groups.txt.D.out"; "groups -D")]
+#[test_case("groups.txt", vec!["--all-repeated"], "groups.txt.D.out"; "groups --all-repeated")]
+#[test_case("groups.txt", vec!["--all-repeated=none"], "groups.txt.D.none.out"; "groups --all-repeated=none")]
+#[test_case("groups.txt", vec!["--all-repeated=prepend"], "groups.txt.D.prepend.out"; "groups --all-repeated=prepend")]
+#[test_case("groups.txt", vec!["--all-repeated=separate"], "groups.txt.D.separate.out"; "groups --all-repeated=separate")]
+#[test_case("groups.txt", vec!["--group"], "groups.txt.group.out"; "groups --group")]
+#[test_case("groups.txt", vec!["--group=separate"], "groups.txt.group.separate.out"; "groups --group=separate")]
+#[test_case("groups.txt", vec!["--group=prepend"], "groups.txt.group.prepend.out"; "groups --group=prepend")]
+#[test_case("groups.txt", vec!["--group=append"], "groups.txt.group.append.out"; "groups --group=append")]
+#[test_case("groups.txt", vec!["--group=both"], "groups.txt.group.both.out"; "groups --group=both")]
+#[test_case("groups.txt", vec!["-c", "--all-repeated=separate"], "groups.txt.c.D.separate.out"; "groups -c --all-repeated=separate")]
+#[test_case("groups.txt", vec!["-c", "--group"], "groups.txt.c.group.out"; "groups -c --group")]
+#[test_case("blank.txt", vec![], "blank.txt.out"; "blank")]
+#[test_case("blank.txt", vec!["-c"], "blank.txt.c.out"; "blank with counts")]
+#[test_case("records.txt", vec!["-z"], "records.txt.z.out"; "records zero terminated")]
+#[test_case("records.txt", vec!["-z", "-c"], "records.txt.z.c.out"; "records zero terminated with counts")]
+#[test_case("records.txt", vec!["-z", "--group"], "records.txt.z.group.out"; "records zero terminated grouped")]
 fn run_in_file_out_file(file: &str, args: Vec<&str>, expected_file: &str) -> TestResult {
     let output_file = format!("{expected_file}.output");
     let expected_file = expected!(expected_file);
@@ -97,6 +160,15 @@ fn run_in_file_out_file(file: &str, args: Vec<&str>, expected_file: &str) -> Tes
 #[test_case("three.txt", vec!["-c"], "three.txt.c.out"; "three with counts")]
 #[test_case("two.txt", vec![], "two.txt.out"; "two")]
 #[test_case("two.txt", vec!["-c"], "two.txt.c.out"; "two with counts")]
+#[test_case("unsorted.txt", vec!["--global"], "unsorted.txt.global.out"; "unsorted --global")]
+#[test_case("unsorted.txt", vec!["--global", "-c"], "unsorted.txt.global.c.out"; "unsorted --global -c")]
+#[test_case("unsorted.txt", vec!["--global", "-i"], "unsorted.txt.global.i.out"; "unsorted --global -i")]
+#[test_case("unsorted.txt", vec!["--global", "-d"], "unsorted.txt.global.d.out"; "unsorted --global -d")]
+#[test_case("unsorted.txt", vec!["--global", "-u"], "unsorted.txt.global.u.out"; "unsorted --global -u")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "1"], "unsorted.txt.global.out"; "unsorted --global spilled")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "150", "-c"], "unsorted.txt.global.c.out"; "unsorted --global -c spilled midway")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "1", "-i"], "unsorted.txt.global.i.out"; "unsorted --global -i spilled")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "1K"], "unsorted.txt.global.out"; "unsorted --global not spilled")]
 fn run_in_file_out_stdout(file: &str, args: Vec<&str>, expected_file: &str) -> TestResult {
     let expected_file = expected!(expected_file);
     let expected = fs::read(&expected_file)?;
@@ -134,6 +206,37 @@ fn run_in_file_out_stdout(file: &str, args: Vec<&str>, expected_file: &str) -> T
 #[test_case("three.txt", vec!["-c"], "three.txt.c.out"; "three with counts")]
 #[test_case("two.txt", vec![], "two.txt.out"; "two")]
 #[test_case("two.txt", vec!["-c"], "two.txt.c.out"; "two with counts")]
+#[test_case("fields.txt", vec!["-d"], "fields.txt.d.out"; "fields -d")]
+#[test_case("fields.txt", vec!["-u"], "fields.txt.u.out"; "fields -u")]
+#[test_case("fields.txt", vec!["-i"], "fields.txt.i.out"; "fields -i")]
+#[test_case("fields.txt", vec!["-f", "1"], "fields.txt.f1.out"; "fields -f 1")]
+#[test_case("fields.txt", vec!["-f", "1", "-i"], "fields.txt.f1.i.out"; "fields -f 1 -i")]
+#[test_case("fields.txt", vec!["-s", "2"], "fields.txt.s2.out"; "fields -s 2")]
+#[test_case("fields.txt", vec!["-w", "1"], "fields.txt.w1.out"; "fields -w 1")]
+#[test_case("fields.txt", vec!["-f", "1", "-w", "6"], "fields.txt.f1.w6.out"; "fields -f 1 -w 6")]
+#[test_case("fields.txt", vec!["-c", "-d"], "fields.txt.c.d.out"; "fields -c -d")]
+#[test_case("fields.txt", vec!["-c", "-u"], "fields.txt.c.u.out"; "fields -c -u")]
+#[test_case("fields.txt", vec!["-d", "-u"], "fields.txt.d.u.out"; "fields -d -u")]
+#[test_case("fields.txt", vec!["-c", "-f", "1", "-s", "1", "-w", "3", "-i"], "fields.txt.c.f1.s1.w3.i.out"; "fields -c -f 1 -s 1 -w 3 -i")]
+#[test_case("fields.txt", vec!["-f", "2"], "fields.txt.f2.out"; "fields -f 2")]
+#[test_case("fields.txt", vec!["-f", "5"], "fields.txt.f5.out"; "fields -f 5")]
+#[test_case("groups.txt", vec!["-D"], "groups.txt.D.out"; "groups -D")]
+#[test_case("groups.txt", vec!["--all-repeated"], "groups.txt.D.out"; "groups --all-repeated")]
+#[test_case("groups.txt", vec!["--all-repeated=none"], "groups.txt.D.none.out"; "groups --all-repeated=none")]
+#[test_case("groups.txt", vec!["--all-repeated=prepend"], "groups.txt.D.prepend.out"; "groups --all-repeated=prepend")]
+#[test_case("groups.txt", vec!["--all-repeated=separate"], "groups.txt.D.separate.out"; "groups --all-repeated=separate")]
+#[test_case("groups.txt", vec!["--group"], "groups.txt.group.out"; "groups --group")]
+#[test_case("groups.txt", vec!["--group=separate"], "groups.txt.group.separate.out"; "groups --group=separate")]
+#[test_case("groups.txt", vec!["--group=prepend"], "groups.txt.group.prepend.out"; "groups --group=prepend")]
+#[test_case("groups.txt", vec!["--group=append"], "groups.txt.group.append.out"; "groups --group=append")]
+#[test_case("groups.txt", vec!["--group=both"], "groups.txt.group.both.out"; "groups --group=both")]
+#[test_case("groups.txt", vec!["-c", "--all-repeated=separate"], "groups.txt.c.D.separate.out"; "groups -c --all-repeated=separate")]
+#[test_case("groups.txt", vec!["-c", "--group"], "groups.txt.c.group.out"; "groups -c --group")]
+#[test_case("blank.txt", vec![], "blank.txt.out"; "blank")]
+#[test_case("blank.txt", vec!["-c"], "blank.txt.c.out"; "blank with counts")]
+#[test_case("records.txt", vec!["-z"], "records.txt.z.out"; "records zero terminated")]
+#[test_case("records.txt", vec!["-z", "-c"], "records.txt.z.c.out"; "records zero terminated with counts")]
+#[test_case("records.txt", vec!["-z", "--group"], "records.txt.z.group.out"; "records zero terminated grouped")]
 fn run_in_stdin_out_file(file: &str, args: Vec<&str>, expected_file: &str) -> TestResult {
     let output_file = format!("{expected_file}.output");
     let expected_file = expected!(expected_file);
@@ -180,6 +283,46 @@ fn run_in_stdin_out_file(file: &str, args: Vec<&str>, expected_file: &str) -> Te
 #[test_case("three.txt", vec!["-c"], "three.txt.c.out"; "three with counts")]
 #[test_case("two.txt", vec![], "two.txt.out"; "two")]
 #[test_case("two.txt", vec!["-c"], "two.txt.c.out"; "two with counts")]
+#[test_case("fields.txt", vec!["-d"], "fields.txt.d.out"; "fields -d")]
+#[test_case("fields.txt", vec!["-u"], "fields.txt.u.out"; "fields -u")]
+#[test_case("fields.txt", vec!["-i"], "fields.txt.i.out"; "fields -i")]
+#[test_case("fields.txt", vec!["-f", "1"], "fields.txt.f1.out"; "fields -f 1")]
+#[test_case("fields.txt", vec!["-f", "1", "-i"], "fields.txt.f1.i.out"; "fields -f 1 -i")]
+#[test_case("fields.txt", vec!["-s", "2"], "fields.txt.s2.out"; "fields -s 2")]
+#[test_case("fields.txt", vec!["-w", "1"], "fields.txt.w1.out"; "fields -w 1")]
+#[test_case("fields.txt", vec!["-f", "1", "-w", "6"], "fields.txt.f1.w6.out"; "fields -f 1 -w 6")]
+#[test_case("fields.txt", vec!["-c", "-d"], "fields.txt.c.d.out"; "fields -c -d")]
+#[test_case("fields.txt", vec!["-c", "-u"], "fields.txt.c.u.out"; "fields -c -u")]
+#[test_case("fields.txt", vec!["-d", "-u"], "fields.txt.d.u.out"; "fields -d -u")]
+#[test_case("fields.txt", vec!["-c", "-f", "1", "-s", "1", "-w", "3", "-i"], "fields.txt.c.f1.s1.w3.i.out"; "fields -c -f 1 -s 1 -w 3 -i")]
+#[test_case("fields.txt", vec!["-f", "2"], "fields.txt.f2.out"; "fields -f 2")]
+#[test_case("fields.txt", vec!["-f", "5"], "fields.txt.f5.out"; "fields -f 5")]
+#[test_case("groups.txt", vec!["-D"], "groups.txt.D.out"; "groups -D")]
+#[test_case("groups.txt", vec!["--all-repeated"], "groups.txt.D.out"; "groups --all-repeated")]
+#[test_case("groups.txt", vec!["--all-repeated=none"], "groups.txt.D.none.out"; "groups --all-repeated=none")]
+#[test_case("groups.txt", vec!["--all-repeated=prepend"], "groups.txt.D.prepend.out"; "groups --all-repeated=prepend")]
+#[test_case("groups.txt", vec!["--all-repeated=separate"], "groups.txt.D.separate.out"; "groups --all-repeated=separate")]
+#[test_case("groups.txt", vec!["--group"], "groups.txt.group.out"; "groups --group")]
+#[test_case("groups.txt", vec!["--group=separate"], "groups.txt.group.separate.out"; "groups --group=separate")]
+#[test_case("groups.txt", vec!["--group=prepend"], "groups.txt.group.prepend.out"; "groups --group=prepend")]
+#[test_case("groups.txt", vec!["--group=append"], "groups.txt.group.append.out"; "groups --group=append")]
+#[test_case("groups.txt", vec!["--group=both"], "groups.txt.group.both.out"; "groups --group=both")]
+#[test_case("groups.txt", vec!["-c", "--all-repeated=separate"], "groups.txt.c.D.separate.out"; "groups -c --all-repeated=separate")]
+#[test_case("groups.txt", vec!["-c", "--group"], "groups.txt.c.group.out"; "groups -c --group")]
+#[test_case("blank.txt", vec![], "blank.txt.out"; "blank")]
+#[test_case("blank.txt", vec!["-c"], "blank.txt.c.out"; "blank with counts")]
+#[test_case("records.txt", vec!["-z"], "records.txt.z.out"; "records zero terminated")]
+#[test_case("records.txt", vec!["-z", "-c"], "records.txt.z.c.out"; "records zero terminated with counts")]
+#[test_case("records.txt", vec!["-z", "--group"], "records.txt.z.group.out"; "records zero terminated grouped")]
+#[test_case("unsorted.txt", vec!["--global"], "unsorted.txt.global.out"; "unsorted --global")]
+#[test_case("unsorted.txt", vec!["--global", "-c"], "unsorted.txt.global.c.out"; "unsorted --global -c")]
+#[test_case("unsorted.txt", vec!["--global", "-i"], "unsorted.txt.global.i.out"; "unsorted --global -i")]
+#[test_case("unsorted.txt", vec!["--global", "-d"], "unsorted.txt.global.d.out"; "unsorted --global -d")]
+#[test_case("unsorted.txt", vec!["--global", "-u"], "unsorted.txt.global.u.out"; "unsorted --global -u")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "1"], "unsorted.txt.global.out"; "unsorted --global spilled")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "150", "-c"], "unsorted.txt.global.c.out"; "unsorted --global -c spilled midway")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "1", "-i"], "unsorted.txt.global.i.out"; "unsorted --global -i spilled")]
+#[test_case("unsorted.txt", vec!["--global", "--max-memory", "1K"], "unsorted.txt.global.out"; "unsorted --global not spilled")]
 fn run_in_stdin_out_stdout(file: &str, args: Vec<&str>, expected_file: &str) -> TestResult {
     let expected_file = expected!(expected_file);
     let expected = fs::read(&expected_file)?;
@@ -194,4 +337,57 @@ fn run_in_stdin_out_stdout(file: &str, args: Vec<&str>, expected_file: &str) ->
         .success()
         .stdout(expected);
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn overwrites_existing_output_file() -> TestResult {
+    let output_file = "overwrite.output";
+    fs::write(output_file, "a much longer content that has to disappear\n".repeat(10))?;
+    Command::cargo_bin(PRG)?
+        .arg(input!("t1.txt"))
+        .arg(output_file)
+        .assert()
+        .success()
+        .stdout(str::is_empty());
+    let actual = fs::read(output_file)?;
+    fs::remove_file(output_file)?;
+    assert_eq!(actual, fs::read(expected!("t1.txt.out"))?);
+    Ok(())
+}
+
+#[test]
+fn appends_to_existing_output_file() -> TestResult {
+    let output_file = "append.output";
+    fs::write(output_file, "existing\n")?;
+    Command::cargo_bin(PRG)?
+        .arg(input!("t1.txt"))
+        .arg(output_file)
+        .arg("--append")
+        .assert()
+        .success()
+        .stdout(str::is_empty());
+    let actual = fs::read(output_file)?;
+    fs::remove_file(output_file)?;
+    let mut expected = b"existing\n".to_vec();
+    expected.extend(fs::read(expected!("t1.txt.out"))?);
+    assert_eq!(actual, expected);
+    Ok(())
+}
+
+#[test]
+fn appends_to_new_output_file() -> TestResult {
+    let output_file = "append-new.output";
+    if std::path::Path::new(output_file).exists() {
+        fs::remove_file(output_file)?;
+    }
+    Command::cargo_bin(PRG)?
+        .arg(input!("t1.txt"))
+        .arg(output_file)
+        .arg("--append")
+        .assert()
+        .success();
+    let actual = fs::read(output_file)?;
+    fs::remove_file(output_file)?;
+    assert_eq!(actual, fs::read(expected!("t1.txt.out"))?);
+    Ok(())
+}
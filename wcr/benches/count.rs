@@ -0,0 +1,63 @@
+//! Compares the line counter used for `-l` with the full counter,
+//! and counting many files with one thread against several.
+//!
+//! Run with `cargo bench --bench count`.
+
+use std::{hint::black_box, io::Cursor, path::PathBuf, process::Command};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const LINE: &str = "The quick brown fox jumps over the lazy dog 0123456789\n";
+const INPUT_SIZE: usize = 16 << 20;
+const FILES: usize = 64;
+const FILE_SIZE: usize = 1 << 20;
+
+fn generate_input(size: usize) -> Vec<u8> {
+    LINE.bytes().cycle().take(size).collect()
+}
+
+fn bench_counters(c: &mut Criterion) {
+    let input = generate_input(INPUT_SIZE);
+    let mut group = c.benchmark_group("counters");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("count", |b| {
+        b.iter(|| wcr::count(&None, Cursor::new(black_box(&input))).unwrap())
+    });
+    group.bench_function("count_lines", |b| {
+        b.iter(|| wcr::count_lines(&None, Cursor::new(black_box(&input))).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_jobs(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = generate_input(FILE_SIZE);
+    let files: Vec<PathBuf> = (0..FILES)
+        .map(|n| {
+            let path = dir.path().join(format!("{n}.txt"));
+            std::fs::write(&path, &input).unwrap();
+            path
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("jobs");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes((FILES * FILE_SIZE) as u64));
+    for jobs in [1, 4] {
+        group.bench_with_input(BenchmarkId::from_parameter(jobs), &jobs, |b, jobs| {
+            b.iter(|| {
+                let output = Command::new(env!("CARGO_BIN_EXE_wcr"))
+                    .arg("-j")
+                    .arg(jobs.to_string())
+                    .args(&files)
+                    .output()
+                    .unwrap();
+                assert!(output.status.success());
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_counters, bench_jobs);
+criterion_main!(benches);
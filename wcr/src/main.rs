@@ -1,6 +1,10 @@
 fn main() {
-    if let Err(error) = wcr::run() {
-        eprintln!("{}", error);
-        std::process::exit(1);
+    match wcr::run() {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
     }
 }
\ No newline at end of file
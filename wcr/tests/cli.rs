@@ -1,6 +1,6 @@
 use assert_cmd::Command;
 use path_macro::path;
-use predicates::str;
+use predicates::{prelude::*, str};
 use std::{fs, path::PathBuf};
 use test_case::test_case;
 
@@ -32,6 +32,16 @@ fn dies_when_both_bytes_and_words() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn dies_when_zero_jobs() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-j", "0", "-"])
+        .assert()
+        .failure()
+        .stderr(str::contains("invalid value '0' for '--jobs <N>'"));
+    Ok(())
+}
+
 #[test_case(vec!["empty.txt"], vec![], "empty.txt.out"; "empty")]
 #[test_case(vec!["empty.txt"], vec!["-c"], "empty.txt.c.out"; "empty bytes")]
 #[test_case(vec!["empty.txt"], vec!["-cl"], "empty.txt.cl.out"; "empty lines bytes")]
@@ -94,3 +104,132 @@ fn run_stdin(file: &str, expected_file: &str) -> TestResult {
         .stdout(expected);
     Ok(())
 }
+
+#[test]
+fn dies_when_both_files0_from_and_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", "files0.txt", "fox.txt"])
+        .assert()
+        .failure()
+        .stderr(str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test_case(vec!["-lL", "fox.txt", "atlamal.txt"], "fox.atlamal.lL.out"; "max line length")]
+#[test_case(vec!["-lwcL", "fox.txt", "atlamal.txt"], "fox.atlamal.lwcL.out"; "all counts with max line length")]
+#[test_case(vec!["-mL", "atlamal.txt"], "atlamal.txt.mL.out"; "chars and max line length")]
+#[test_case(vec!["--files0-from", "files0.txt"], "files0.txt.out"; "files0 from file")]
+#[test_case(vec!["-wL", "--files0-from", "files0.txt"], "files0.txt.wL.out"; "files0 from file with words and max line length")]
+#[test_case(vec!["--total=always", "fox.txt"], "fox.txt.total.always.out"; "total always")]
+#[test_case(vec!["--total=never", "fox.txt", "atlamal.txt"], "fox.atlamal.total.never.out"; "total never")]
+#[test_case(vec!["--total=only", "fox.txt", "atlamal.txt"], "fox.atlamal.total.only.out"; "total only")]
+#[test_case(vec!["--total=auto", "fox.txt", "atlamal.txt"], "fox.atlamal.out"; "total auto")]
+#[test_case(vec!["--format", "json", "fox.txt", "atlamal.txt"], "fox.atlamal.json.out"; "json")]
+#[test_case(vec!["--format", "csv", "-lmL", "fox.txt", "atlamal.txt"], "fox.atlamal.lmL.csv.out"; "csv with selected counts")]
+#[test_case(vec!["--format", "csv", "--total=always", "fox.txt"], "fox.txt.total.always.csv.out"; "csv total always")]
+#[test_case(vec!["--format", "tsv", "--total=only", "fox.txt", "atlamal.txt"], "fox.atlamal.total.only.tsv.out"; "tsv total only")]
+#[test_case(vec!["binary.bin"], "binary.bin.out"; "binary")]
+#[test_case(vec!["-lwmL", "binary.bin"], "binary.bin.lwmL.out"; "binary lines words chars max line length")]
+#[test_case(vec!["latin1.txt"], "latin1.txt.out"; "latin1")]
+#[test_case(vec!["-lwmL", "latin1.txt"], "latin1.txt.lwmL.out"; "latin1 lines words chars max line length")]
+#[test_case(vec!["-lc", "fox.txt", "atlamal.txt", "binary.bin"], "fox.atlamal.binary.lc.out"; "lines and bytes")]
+#[test_case(vec!["-j", "2", "fox.txt", "atlamal.txt"], "fox.atlamal.out"; "two jobs")]
+#[test_case(vec!["-j", "8", "-lc", "fox.txt", "atlamal.txt", "binary.bin"], "fox.atlamal.binary.lc.out"; "more jobs than files")]
+#[test_case(vec!["--jobs", "3", "--files0-from", "files0.txt"], "files0.txt.out"; "jobs with files0 from file")]
+fn run_in_inputs(args: Vec<&str>, expected_file: &str) -> TestResult {
+    let expected_file = expected!(expected_file);
+    let expected = fs::read(&expected_file)?;
+    let expected = String::from_utf8_lossy(&expected).into_owned();
+
+    Command::cargo_bin(PRG)?
+        .current_dir(path!("tests" / "inputs"))
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn files0_from_stdin() -> TestResult {
+    let expected = fs::read(expected!("files0.txt.out"))?;
+    let expected = String::from_utf8_lossy(&expected).into_owned();
+
+    Command::cargo_bin(PRG)?
+        .current_dir(path!("tests" / "inputs"))
+        .args(["--files0-from", "-"])
+        .write_stdin("fox.txt\0atlamal.txt\0empty.txt\0")
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn json_reports_file_errors() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .current_dir(path!("tests" / "inputs"))
+        .args(["--format", "json", "fox.txt", "nonexistent.txt"])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(str::starts_with("wcr: nonexistent.txt: "))
+        .stdout(str::contains(r#"{"name":"fox.txt","num_lines":1,"num_words":9,"num_bytes":48,"num_chars":null,"max_line_length":null,"error":null}"#))
+        .stdout(str::contains(r#"{"name":"nonexistent.txt","num_lines":null,"num_words":null,"num_bytes":null,"num_chars":null,"max_line_length":null,"error":""#))
+        .stdout(str::contains(r#"{"name":"total","num_lines":1,"#));
+    Ok(())
+}
+
+#[test]
+fn csv_reports_file_errors() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .current_dir(path!("tests" / "inputs"))
+        .args(["--format", "csv", "nonexistent.txt", "fox.txt"])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(str::starts_with("wcr: nonexistent.txt: "))
+        .stdout(str::starts_with("name,num_lines,num_words,num_bytes,num_chars,max_line_length,error\nnonexistent.txt,,,,,,"))
+        .stdout(str::ends_with("fox.txt,1,9,48,,,\ntotal,1,9,48,,,\n"));
+    Ok(())
+}
+
+#[test]
+fn skips_bad_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .current_dir(path!("tests" / "inputs"))
+        .args(["fox.txt", "nonexistent.txt", "atlamal.txt"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout("  1   9  48 fox.txt\n  4  29 173 atlamal.txt\n  5  38 221 total\n")
+        .stderr(str::is_match("^wcr: nonexistent.txt: .+\n$")?);
+    Ok(())
+}
+
+#[test]
+fn fails_reading_directory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .current_dir("tests")
+        .args(["inputs", "inputs/fox.txt"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(str::contains("total"))
+        .stderr(str::starts_with("wcr: inputs: "))
+        .stderr(str::contains("panicked").not());
+    Ok(())
+}
+
+#[test]
+fn only_total_with_bad_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .current_dir(path!("tests" / "inputs"))
+        .args(["--total=only", "nonexistent.txt", "fox.txt"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(" 1  9 48\n")
+        .stderr(str::starts_with("wcr: nonexistent.txt: "));
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use wcr::FileInfo;
+use wcr::{FileInfo, Whitespace};
 use std::io::Cursor;
 
 #[test]
@@ -12,6 +12,60 @@ fn test_count() {
         num_words: 10,
         num_chars: 48,
         num_bytes: 48,
+        max_line_length: 46,
     };
     assert_eq!(info.unwrap(), expected);
 }
+
+#[test]
+fn test_serialize() {
+    let info = wcr::count(&Some("filename"), Cursor::new("one two\nthree\n")).unwrap();
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(
+        json,
+        r#"{"name":"filename","num_lines":2,"num_words":3,"num_bytes":14,"num_chars":14,"max_line_length":7}"#
+    );
+}
+
+#[test]
+fn test_count_invalid_utf8() {
+    let bytes: &[u8] = b"caf\xe9 na\xefve\n\xff\xfe\x00 end";
+    let info = wcr::count(&None, Cursor::new(bytes)).unwrap();
+    assert_eq!(info.num_lines, 1);
+    assert_eq!(info.num_words, 4);
+    assert_eq!(info.num_bytes, 18);
+    assert_eq!(info.num_chars, 18);
+}
+
+#[test]
+fn test_count_whitespace() {
+    let text = "one\u{a0}two\u{2003}three\n";
+    let info = wcr::count(&None, Cursor::new(text)).unwrap();
+    assert_eq!(info.num_words, 1);
+    let info = wcr::count_with_whitespace(&None, Cursor::new(text), Whitespace::Unicode).unwrap();
+    assert_eq!(info.num_words, 3);
+    assert_eq!(info.num_chars, 14);
+}
+
+#[test]
+fn test_count_lines() {
+    let text = "one\ntwo\n\nthree";
+    let info = wcr::count_lines(&Some("filename"), Cursor::new(text)).unwrap();
+    let expected = FileInfo {
+        name: Some(String::from("filename")),
+        num_lines: 3,
+        num_words: 0,
+        num_chars: 0,
+        num_bytes: 14,
+        max_line_length: 0,
+    };
+    assert_eq!(info, expected);
+}
+
+#[test]
+fn test_count_display_width() {
+    let mut bytes = "日本語\tx\nabc\u{301}".as_bytes().to_vec();
+    bytes.extend_from_slice(b"\xff\xfe\n");
+    let info = wcr::count(&None, Cursor::new(bytes)).unwrap();
+    assert_eq!(info.max_line_length, 9);
+}